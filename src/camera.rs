@@ -0,0 +1,110 @@
+use super::{Map, Position, Renderable, TileType};
+use rltk::{Point, Rltk, RGB};
+use specs::prelude::*;
+
+/// The part of the screen the map is drawn in. The rest is taken up by the UI.
+pub const VIEW_WIDTH: i32 = 80;
+pub const VIEW_HEIGHT: i32 = 43;
+
+/// Returns the map coordinates shown in the top left corner of the screen.
+/// The camera follows the player, but stops at the edges of the map so no space is wasted.
+/// Maps smaller than the screen are centred instead.
+pub fn get_view_origin(ecs: &World) -> (i32, i32) {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+
+    let origin_for = |player: i32, map_size: i32, view_size: i32| {
+        if map_size <= view_size {
+            -(view_size - map_size) / 2
+        } else {
+            (player - view_size / 2).clamp(0, map_size - view_size)
+        }
+    };
+
+    (
+        origin_for(player_pos.x, map.width, VIEW_WIDTH),
+        origin_for(player_pos.y, map.height, VIEW_HEIGHT),
+    )
+}
+
+/// Converts a position on the screen, such as the mouse, to map coordinates.
+/// Returns `None` if it lies outside of the map view.
+pub fn screen_to_map(ecs: &World, screen_x: i32, screen_y: i32) -> Option<(i32, i32)> {
+    if !in_view(screen_x, screen_y) {
+        return None;
+    }
+    let (origin_x, origin_y) = get_view_origin(ecs);
+    let map = ecs.fetch::<Map>();
+    let (x, y) = (screen_x + origin_x, screen_y + origin_y);
+    if x < 0 || x >= map.width || y < 0 || y >= map.height {
+        return None;
+    }
+    Some((x, y))
+}
+
+/// Moves a map position onto the nearest tile that is both on the map and on the screen.
+pub fn clamp_to_view(ecs: &World, x: i32, y: i32) -> (i32, i32) {
+    let (origin_x, origin_y) = get_view_origin(ecs);
+    let map = ecs.fetch::<Map>();
+    let clamp = |pos: i32, origin: i32, map_size: i32, view_size: i32| {
+        pos.clamp(
+            i32::max(0, origin),
+            i32::min(map_size, origin + view_size) - 1,
+        )
+    };
+    (
+        clamp(x, origin_x, map.width, VIEW_WIDTH),
+        clamp(y, origin_y, map.height, VIEW_HEIGHT),
+    )
+}
+
+/// Draws the part of the map around the player, and every visible entity on it.
+pub fn render_camera(ecs: &World, ctx: &mut Rltk) {
+    let (origin_x, origin_y) = get_view_origin(ecs);
+    let map = ecs.fetch::<Map>();
+
+    for screen_y in 0..VIEW_HEIGHT {
+        for screen_x in 0..VIEW_WIDTH {
+            let (x, y) = (screen_x + origin_x, screen_y + origin_y);
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+
+            let idx = map.xy_idx(x, y);
+            if map.revealed_tiles[idx] {
+                let (glyph, mut fg) = tile_glyph(map.tiles[idx]);
+                if !map.visible_tiles[idx] {
+                    fg = fg.to_greyscale()
+                }
+                ctx.set(screen_x, screen_y, fg, RGB::from_f32(0., 0., 0.), glyph);
+            }
+        }
+    }
+
+    // Things on the same tile are drawn in reverse render order, leaving the lowest on top
+    let positions = ecs.read_storage::<Position>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let mut data = (&positions, &renderables).join().collect::<Vec<_>>();
+    data.sort_by_key(|(_, render)| std::cmp::Reverse(render.render_order));
+    for (pos, render) in data.iter() {
+        let idx = map.xy_idx(pos.x, pos.y);
+        let (screen_x, screen_y) = (pos.x - origin_x, pos.y - origin_y);
+        if map.visible_tiles[idx] && in_view(screen_x, screen_y) {
+            ctx.set(screen_x, screen_y, render.fg, render.bg, render.glyph)
+        }
+    }
+}
+
+fn in_view(screen_x: i32, screen_y: i32) -> bool {
+    (0..VIEW_WIDTH).contains(&screen_x) && (0..VIEW_HEIGHT).contains(&screen_y)
+}
+
+fn tile_glyph(tile: TileType) -> (rltk::FontCharType, RGB) {
+    match tile {
+        TileType::Floor => (rltk::to_cp437('.'), RGB::from_f32(0.0, 0.5, 0.5)),
+        TileType::Wall => (rltk::to_cp437('#'), RGB::from_f32(0., 1.0, 0.)),
+        TileType::DownStairs => (rltk::to_cp437('>'), RGB::from_f32(0., 1.0, 1.0)),
+        TileType::ClosedDoor => (rltk::to_cp437('+'), RGB::from_f32(0.8, 0.5, 0.2)),
+        TileType::OpenDoor => (rltk::to_cp437('\''), RGB::from_f32(0.8, 0.5, 0.2)),
+    }
+}
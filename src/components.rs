@@ -1,42 +1,48 @@
 use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use specs::error::NoError;
 use specs::prelude::*;
+use specs::saveload::{ConvertSaveload, Marker};
 use specs_derive::*;
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Position {
     pub x: i32,
     pub y: i32,
 }
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Renderable {
     pub glyph: rltk::FontCharType,
     pub fg: RGB,
     pub bg: RGB,
+    /// When several things share a tile, the lowest order is drawn on top: 0 for the player, 1
+    /// for monsters and 2 for items.
+    pub render_order: i32,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
 pub struct Player {}
 
-#[derive(Component)]
+#[derive(Component, Serialize, Deserialize, Clone)]
 pub struct Viewshed {
     pub visible_tiles: Vec<rltk::Point>,
     pub range: i32,
     pub dirty: bool,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
 pub struct Monster {}
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
 pub struct Name {
     pub name: String,
 }
 
-#[derive(Debug, Component)]
+#[derive(Debug, Component, Serialize, Deserialize, Clone)]
 pub struct BlocksTile {}
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct CombatStats {
     pub max_hp: i32,
     pub curr_hp: i32,
@@ -47,25 +53,175 @@ pub struct CombatStats {
     pub curr_mana: i32,
 }
 
-#[derive(Component, Debug, Clone)]
+#[derive(Component, Debug, ConvertSaveload, Clone)]
 pub struct WantsToMelee {
     pub target: Entity,
 }
 
-#[derive(Component, Debug)]
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
 pub struct SufferDamage {
     pub amount: Vec<i32>,
+    /// Name of whoever dealt the most recent damage, in case it turns out to be fatal.
+    pub last_hit_by: String,
 }
 
 impl SufferDamage {
-    pub fn new_damage(store: &mut WriteStorage<SufferDamage>, victim: Entity, amount: i32) {
+    pub fn new_damage(
+        store: &mut WriteStorage<SufferDamage>,
+        victim: Entity,
+        amount: i32,
+        source: &str,
+    ) {
         if let Some(suffering) = store.get_mut(victim) {
             suffering.amount.push(amount);
+            suffering.last_hit_by = source.to_string();
         } else {
             let dmg = SufferDamage {
                 amount: vec![amount],
+                last_hit_by: source.to_string(),
             };
             store.insert(victim, dmg).expect("Unable to insert damage");
         }
     }
 }
+
+/// Added to an entity when its hit points run out, recording what killed it.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct KilledBy {
+    pub name: String,
+}
+
+/// Lets an entity take turns. Every tick it gains `speed` energy, and once it has
+/// `initiative_system::ACTION_COST` it may act. Acting uses the energy up again.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Energy {
+    pub speed: i32,
+    pub energy: i32,
+    /// What a melee attack costs. Heavy hitters take longer than `ACTION_COST` to swing.
+    pub attack_cost: i32,
+}
+
+/// Marks entities that are ready to act this tick.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct MyTurn {}
+
+/// Something that can be picked up and carried.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Item {}
+
+/// An item being carried. It has no `Position` while it is in a backpack.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct InBackpack {
+    pub owner: Entity,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToPickupItem {
+    pub collected_by: Entity,
+    pub item: Entity,
+}
+
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToDropItem {
+    pub item: Entity,
+}
+
+/// `target` is the tile a ranged item is used on. Other items are used on whoever uses them.
+#[derive(Component, Debug, ConvertSaveload, Clone)]
+pub struct WantsToUseItem {
+    pub item: Entity,
+    pub target: Option<rltk::Point>,
+}
+
+/// Used on a tile the player picks, up to `range` tiles away, rather than on the player.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Ranged {
+    pub range: i32,
+}
+
+/// Hurts whatever it is used on.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct InflictsDamage {
+    pub damage: i32,
+}
+
+/// Used up when it is used.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Consumable {}
+
+/// Heals whoever uses it, up to their maximum HP.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct ProvidesHealing {
+    pub heal_amount: i32,
+}
+
+/// Gives mana back to whoever uses it, up to their maximum.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct RestoresMana {
+    pub mana_amount: i32,
+}
+
+/// Raises the maximum HP of whoever uses it. The new hit points come full.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct IncreasesMaxHp {
+    pub amount: i32,
+}
+
+/// A spell, copied from its template in the raws when it is learnt.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Spell {
+    pub name: String,
+    pub description: String,
+    pub mana_cost: i32,
+    /// Spells with a range are cast on a tile the caster picks, rather than on the caster.
+    pub range: Option<i32>,
+    /// Everyone this many tiles from the target is caught as well. 0 only hits the target tile.
+    pub radius: i32,
+    /// Magic damage, lessened by the `magic_res` of whoever it hits.
+    pub damage: Option<i32>,
+    pub healing: Option<i32>,
+    /// Slows whoever it hits for that many of their turns.
+    pub slows: Option<i32>,
+}
+
+/// The spells an entity can cast, in the order the spellbook lists them.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct KnownSpells {
+    pub spells: Vec<Spell>,
+}
+
+/// `target` is the tile a spell with a range is cast on. Other spells are cast on the caster.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct WantsToCastSpell {
+    pub spell: Spell,
+    pub target: Option<rltk::Point>,
+}
+
+/// Gains energy at half its usual speed, for `turns` more of its turns.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Slowed {
+    pub turns: i32,
+}
+
+/// What the player reads when examining something.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Description {
+    pub text: String,
+}
+
+/// Takes no damage at all, such as the player in the wizard console's god mode.
+#[derive(Component, Debug, Serialize, Deserialize, Clone)]
+pub struct Invulnerable {}
+
+/// Marks entities that are written to the save game.
+pub struct SerializeMe;
+
+/// Carries the game's resources (the map and so on) into the save game, since only entities
+/// and their components get serialized.
+#[derive(Component, Serialize, Deserialize, Clone)]
+pub struct SerializationHelper {
+    pub map: super::map::Map,
+    pub log: super::gamelog::GameLog,
+    pub rng: rltk::RandomNumberGenerator,
+    pub seed: u64,
+}
@@ -1,20 +1,36 @@
-use super::{gamelog::GameLog, CombatStats, Name, Player, SufferDamage};
-use rltk::console;
+use super::{
+    gamelog::{GameLog, LogCategory, LogEntry},
+    CombatStats, Invulnerable, KilledBy, Name, Player, Renderable, RunState, SufferDamage,
+};
 use specs::prelude::*;
 
 pub struct DamageSystem {}
 
 impl<'a> System<'a> for DamageSystem {
     type SystemData = (
+        Entities<'a>,
         WriteStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, KilledBy>,
+        ReadStorage<'a, Invulnerable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut stats, mut damage) = data;
+        let (entities, mut stats, mut damage, mut killed_by, invulnerable) = data;
 
-        for (mut stats, damage) in (&mut stats, &damage).join() {
+        for (entity, stats, damage, ()) in (&entities, &mut stats, &damage, !&invulnerable).join() {
+            let was_alive = stats.curr_hp > 0;
             stats.curr_hp -= damage.amount.iter().sum::<i32>();
+            if was_alive && stats.curr_hp < 1 {
+                killed_by
+                    .insert(
+                        entity,
+                        KilledBy {
+                            name: damage.last_hit_by.clone(),
+                        },
+                    )
+                    .expect("Unable to insert cause of death");
+            }
         }
 
         damage.clear();
@@ -23,11 +39,13 @@ impl<'a> System<'a> for DamageSystem {
 
 pub fn delete_the_dead(ecs: &mut World) {
     let mut dead: Vec<Entity> = Vec::new();
+    let mut player_died = false;
     // Using a scope to make the borrow checker happy
     {
         let combat_stats = ecs.read_storage::<CombatStats>();
         let players = ecs.read_storage::<Player>();
         let names = ecs.read_storage::<Name>();
+        let renderables = ecs.read_storage::<Renderable>();
         let entities = ecs.entities();
         let mut log = ecs.write_resource::<GameLog>();
         for (entity, stats) in (&entities, &combat_stats).join() {
@@ -35,13 +53,17 @@ pub fn delete_the_dead(ecs: &mut World) {
                 let player = players.get(entity);
                 match player {
                     None => {
-                        let victim_name = names.get(entity);
-                        if let Some(victim_name) = victim_name {
-                            log.entries.push(format!("{} is dead", &victim_name.name));
+                        if names.contains(entity) {
+                            log.add(
+                                LogEntry::new(LogCategory::Combat)
+                                    .name_of(entity, &names, &renderables)
+                                    .append(" is dead"),
+                            );
                         }
                         dead.push(entity)
                     }
-                    Some(_) => console::log("You are dead"),
+                    // The player is kept around, so the game over screen can tell what happened
+                    Some(_) => player_died = true,
                 }
             }
         }
@@ -50,4 +72,8 @@ pub fn delete_the_dead(ecs: &mut World) {
     for victim in dead {
         ecs.delete_entity(victim).expect("Unable to delete");
     }
+
+    if player_died {
+        *ecs.write_resource::<RunState>() = RunState::GameOver;
+    }
 }
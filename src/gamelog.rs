@@ -1,3 +1,296 @@
+use super::{Name, Renderable};
+use rltk::RGB;
+use serde::{Deserialize, Serialize};
+use specs::prelude::*;
+use std::collections::VecDeque;
+
+/// The most entries kept around. Older ones are forgotten.
+pub const MAX_LOG_ENTRIES: usize = 500;
+
+#[derive(PartialEq, Copy, Clone, Debug, Serialize, Deserialize)]
+pub enum LogCategory {
+    Combat,
+    System,
+    Loot,
+}
+
+/// A run of text in a single colour.
+#[derive(PartialEq, Clone, Debug, Serialize, Deserialize)]
+pub struct LogSpan {
+    pub text: String,
+    pub colour: RGB,
+}
+
+/// One line of the log, built up from coloured spans:
+///
+/// ```ignore
+/// LogEntry::new(LogCategory::Combat)
+///     .colour(RGB::named(rltk::RED))
+///     .append("Goblin #4")
+///     .colour(RGB::named(rltk::WHITE))
+///     .append(" is dead")
+/// ```
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub category: LogCategory,
+    pub spans: Vec<LogSpan>,
+    /// The turn it (last) happened on.
+    pub turn: u32,
+    /// How many times in a row it happened.
+    pub count: u32,
+    #[serde(skip, default = "default_colour")]
+    current_colour: RGB,
+}
+
+fn default_colour() -> RGB {
+    RGB::named(rltk::WHITE)
+}
+
+impl LogEntry {
+    pub fn new(category: LogCategory) -> LogEntry {
+        LogEntry {
+            category,
+            spans: Vec::new(),
+            turn: 0,
+            count: 1,
+            current_colour: default_colour(),
+        }
+    }
+
+    /// Sets the colour of the text appended after this.
+    pub fn colour(mut self, colour: RGB) -> LogEntry {
+        self.current_colour = colour;
+        self
+    }
+
+    pub fn append<S: ToString>(mut self, text: S) -> LogEntry {
+        self.spans.push(LogSpan {
+            text: text.to_string(),
+            colour: self.current_colour,
+        });
+        self
+    }
+
+    /// Appends the name of `entity` in the colour it is drawn in, then goes back to white.
+    pub fn name_of(
+        self,
+        entity: Entity,
+        names: &ReadStorage<Name>,
+        renderables: &ReadStorage<Renderable>,
+    ) -> LogEntry {
+        let colour = renderables
+            .get(entity)
+            .map_or(default_colour(), |renderable| renderable.fg);
+        let name = names.get(entity).map_or("something", |name| &name.name);
+        self.colour(colour).append(name).colour(default_colour())
+    }
+
+    /// Appends how much damage was done, as in "Goblin #4 hits you, for 3 hp."
+    pub fn damage(self, amount: i32) -> LogEntry {
+        self.append(", for ")
+            .colour(RGB::named(rltk::ORANGE))
+            .append(amount)
+            .colour(default_colour())
+            .append(" hp.")
+    }
+
+    /// Finishes an entry like "You use the Wand": who it was used on, unless that was `user`
+    /// themselves, and what it did to them.
+    pub fn used_on(
+        self,
+        user: Entity,
+        target: Option<Entity>,
+        effects: &[String],
+        names: &ReadStorage<Name>,
+        renderables: &ReadStorage<Renderable>,
+    ) -> LogEntry {
+        let mut entry = self;
+        if let Some(target) = target.filter(|target| *target != user) {
+            entry = entry.append(" on ").name_of(target, names, renderables);
+        }
+        if !effects.is_empty() {
+            entry = entry.append(", ").append(effects.join(", "));
+        }
+        entry.append(".")
+    }
+
+    /// The entry's text without colours, including the "x3" for repeats.
+    pub fn text(&self) -> String {
+        let mut text: String = self.spans.iter().map(|span| span.text.as_str()).collect();
+        if self.count > 1 {
+            text += &format!(" x{}", self.count);
+        }
+        text
+    }
+
+    /// The entry's spans split into lines no wider than `width`. Lines are broken between
+    /// words where possible.
+    pub fn wrap(&self, width: usize) -> Vec<Vec<LogSpan>> {
+        let mut spans = self.spans.clone();
+        if self.count > 1 {
+            spans.push(LogSpan {
+                text: format!(" x{}", self.count),
+                colour: RGB::named(rltk::GREY),
+            });
+        }
+
+        let mut lines = vec![Vec::new()];
+        let mut line_width = 0;
+        for span in spans.iter() {
+            // Spaces stay attached to the end of the word before them
+            for word in span.text.split_inclusive(' ') {
+                let mut word: Vec<char> = word.chars().collect();
+                let word_width = word.iter().filter(|c| **c != ' ').count();
+                if line_width > 0 && line_width + word_width > width {
+                    new_line(&mut lines);
+                    line_width = 0;
+                }
+
+                // Words too long for any line are cut up
+                while word_width > width && word.len() > width - line_width {
+                    let piece: String = word.drain(..width - line_width).collect();
+                    push_text(lines.last_mut().unwrap(), &piece, span.colour);
+                    new_line(&mut lines);
+                    line_width = 0;
+                }
+
+                // A space at the very end of a line is dropped
+                word.truncate(width - line_width);
+                line_width += word.len();
+                let word: String = word.into_iter().collect();
+                push_text(lines.last_mut().unwrap(), &word, span.colour);
+            }
+        }
+        lines
+    }
+}
+
+/// Starts a new line, dropping the space left at the end of the previous one.
+fn new_line(lines: &mut Vec<Vec<LogSpan>>) {
+    if let Some(line) = lines.last_mut() {
+        if let Some(last) = line.last_mut() {
+            last.text.truncate(last.text.trim_end().len());
+        }
+        line.retain(|span| !span.text.is_empty());
+    }
+    lines.push(Vec::new());
+}
+
+/// Adds text to the end of a line, joining it onto the last span if it is the same colour.
+fn push_text(line: &mut Vec<LogSpan>, text: &str, colour: RGB) {
+    if text.is_empty() {
+        return;
+    }
+    match line.last_mut() {
+        Some(last) if last.colour == colour => last.text.push_str(text),
+        _ => line.push(LogSpan {
+            text: text.to_string(),
+            colour,
+        }),
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
 pub struct GameLog {
-    pub entries: Vec<String>,
+    entries: VecDeque<LogEntry>,
+    /// How many turns the player has taken, stamped on every new entry.
+    pub turn: u32,
+}
+
+impl GameLog {
+    pub fn new() -> GameLog {
+        GameLog::default()
+    }
+
+    /// Adds an entry. If it says the same as the newest one, that one gets counted again instead.
+    pub fn add(&mut self, mut entry: LogEntry) {
+        entry.turn = self.turn;
+        if let Some(last) = self.entries.back_mut() {
+            if last.category == entry.category && last.spans == entry.spans {
+                last.count += 1;
+                last.turn = entry.turn;
+                return;
+            }
+        }
+
+        self.entries.push_back(entry);
+        while self.entries.len() > MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+    }
+
+    /// Adds an entry in plain white.
+    pub fn log<S: ToString>(&mut self, category: LogCategory, text: S) {
+        self.add(LogEntry::new(category).append(text));
+    }
+
+    /// Oldest first.
+    pub fn entries(&self) -> impl DoubleEndedIterator<Item = &LogEntry> + ExactSizeIterator {
+        self.entries.iter()
+    }
+
+    /// The entries in the `filter` category (or all of them), wrapped to `width`, oldest first.
+    pub fn history(&self, filter: Option<LogCategory>, width: usize) -> Vec<HistoryLine> {
+        let mut lines = Vec::new();
+        for entry in self
+            .entries()
+            .filter(|entry| filter.is_none_or(|category| entry.category == category))
+        {
+            for (i, line) in entry.wrap(width).into_iter().enumerate() {
+                let turn = if i == 0 { Some(entry.turn) } else { None };
+                lines.push((turn, line));
+            }
+        }
+        lines
+    }
+}
+
+/// A line of the message history. The first line of every entry comes with the turn it
+/// happened on.
+pub type HistoryLine = (Option<u32>, Vec<LogSpan>);
+
+/// Ways of moving around the message history.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum HistoryAction {
+    Older,
+    Newer,
+    OlderPage,
+    NewerPage,
+    Oldest,
+    Newest,
+    /// Shows the next category of messages, going round All, Combat, System and Loot.
+    NextFilter,
+}
+
+/// How far back the history is scrolled, and which category it shows, after `action`. `scroll`
+/// is the number of lines hidden below the bottom, so 0 shows the newest; it stops once the
+/// oldest of `line_count` lines is at the top of a `page` of lines.
+pub fn scroll_history(
+    action: HistoryAction,
+    scroll: usize,
+    filter: Option<LogCategory>,
+    line_count: usize,
+    page: usize,
+) -> (usize, Option<LogCategory>) {
+    let max_scroll = line_count.saturating_sub(page);
+    let scroll = match action {
+        HistoryAction::Older => scroll + 1,
+        HistoryAction::Newer => scroll.saturating_sub(1),
+        HistoryAction::OlderPage => scroll + page,
+        HistoryAction::NewerPage => scroll.saturating_sub(page),
+        HistoryAction::Oldest => max_scroll,
+        HistoryAction::Newest => 0,
+        // The newest messages of the new filter are shown first
+        HistoryAction::NextFilter => return (0, next_filter(filter)),
+    };
+    (scroll.min(max_scroll), filter)
+}
+
+fn next_filter(filter: Option<LogCategory>) -> Option<LogCategory> {
+    match filter {
+        None => Some(LogCategory::Combat),
+        Some(LogCategory::Combat) => Some(LogCategory::System),
+        Some(LogCategory::System) => Some(LogCategory::Loot),
+        Some(LogCategory::Loot) => None,
+    }
 }
@@ -1,7 +1,26 @@
-use super::{gamelog::GameLog, CombatStats, Map, Name, Player, Position};
-use rltk::{Point, Rltk, RGB};
+use super::{
+    camera,
+    gamelog::{scroll_history, GameLog, HistoryAction, LogCategory, LogEntry, LogSpan},
+    player::tiles_in_range,
+    saveload_system, CombatStats, Description, GameSeed, InBackpack, KilledBy, KnownSpells, Map,
+    Monster, Name, Player, Position, Renderable, RunState,
+};
+use rltk::{Point, Rltk, VirtualKeyCode, RGB};
 use specs::prelude::*;
 
+/// The space the log gets inside the box at the bottom of the screen.
+const LOG_WIDTH: usize = 76;
+const LOG_LINES: usize = 5;
+
+/// Prints a line of coloured log text, one span after the other.
+fn print_spans(ctx: &mut Rltk, x: i32, y: i32, spans: &[LogSpan]) {
+    let mut x = x;
+    for span in spans.iter() {
+        ctx.print_color(x, y, span.colour, RGB::named(rltk::BLACK), &span.text);
+        x += span.text.chars().count() as i32;
+    }
+}
+
 pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
     ctx.draw_box(
         0,
@@ -12,6 +31,16 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         RGB::named(rltk::BLACK),
     );
 
+    let map = ecs.fetch::<Map>();
+    let depth = format!("Depth: {}", map.depth);
+    ctx.print_color(
+        2,
+        43,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &depth,
+    );
+
     let combat_stats = ecs.read_storage::<CombatStats>();
     let players = ecs.read_storage::<Player>();
     for (_player, stats) in (&players, &combat_stats).join() {
@@ -27,21 +56,55 @@ pub fn draw_ui(ecs: &World, ctx: &mut Rltk) {
         ctx.draw_bar_horizontal(
             28,
             43,
-            51,
+            20,
             stats.curr_hp,
             stats.max_hp,
             RGB::named(rltk::RED),
             RGB::named(rltk::BLACK),
         );
+
+        let mana = format!(" MP: {} / {} ", stats.curr_mana, stats.max_mana);
+        ctx.print_color(
+            49,
+            43,
+            RGB::named(rltk::CYAN),
+            RGB::named(rltk::BLACK),
+            &mana,
+        );
+        ctx.draw_bar_horizontal(
+            65,
+            43,
+            13,
+            stats.curr_mana,
+            stats.max_mana,
+            RGB::named(rltk::BLUE),
+            RGB::named(rltk::BLACK),
+        );
     }
 
+    let seed = format!(" Seed: {} ", ecs.fetch::<GameSeed>().seed);
+    ctx.print_color(
+        2,
+        49,
+        RGB::named(rltk::GREY),
+        RGB::named(rltk::BLACK),
+        &seed,
+    );
+
+    // The newest lines of the log, oldest at the top
     let log = ecs.fetch::<GameLog>();
-    let mut y = 44;
-    for s in log.entries.iter().rev() {
-        if y < 49 {
-            ctx.print(2, y, s);
+    let mut lines: Vec<Vec<LogSpan>> = Vec::new();
+    for entry in log.entries().rev() {
+        for line in entry.wrap(LOG_WIDTH).into_iter().rev() {
+            lines.push(line);
+        }
+        if lines.len() >= LOG_LINES {
+            break;
         }
-        y += 1;
+    }
+    lines.truncate(LOG_LINES);
+    for (i, line) in lines.iter().rev().enumerate() {
+        print_spans(ctx, 2, 44 + i as i32, line);
     }
 
     // Draw mouse cursor
@@ -56,13 +119,14 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
     let positions = ecs.read_storage::<Position>();
 
     let mouse_pos = ctx.mouse_pos();
-    if mouse_pos.0 >= map.width || mouse_pos.1 >= map.height {
-        return;
-    }
+    let (mouse_map_x, mouse_map_y) = match camera::screen_to_map(ecs, mouse_pos.0, mouse_pos.1) {
+        Some(map_pos) => map_pos,
+        None => return,
+    };
     let mut tooltip: Vec<String> = Vec::new();
     for (name, position) in (&names, &positions).join() {
         let idx = map.xy_idx(position.x, position.y);
-        if position.x == mouse_pos.0 && position.y == mouse_pos.1 && map.visible_tiles[idx] {
+        if position.x == mouse_map_x && position.y == mouse_map_y && map.visible_tiles[idx] {
             tooltip.push(name.name.to_string());
         }
     }
@@ -79,8 +143,8 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
         if mouse_pos.0 > 40 {
             let arrow_pos = Point::new(mouse_pos.0 - 2, mouse_pos.1);
             let left_x = mouse_pos.0 - width;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (i, s) in tooltip.iter().enumerate() {
+                let y = mouse_pos.1 + i as i32;
                 ctx.print_color(
                     left_x,
                     y,
@@ -95,23 +159,22 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"->".to_string(),
+                "->",
             );
         } else {
             let arrow_pos = Point::new(mouse_pos.0 + 1, mouse_pos.1);
             let left_x = mouse_pos.0 + 3;
-            let mut y = mouse_pos.1;
-            for s in tooltip.iter() {
+            for (i, s) in tooltip.iter().enumerate() {
+                let y = mouse_pos.1 + i as i32;
                 ctx.print_color(
                     left_x + 1,
                     y,
@@ -126,18 +189,552 @@ fn draw_tooltips(ecs: &World, ctx: &mut Rltk) {
                         y,
                         RGB::named(rltk::WHITE),
                         RGB::named(rltk::GREY),
-                        &" ".to_string(),
+                        " ",
                     );
                 }
-                y += 1;
             }
             ctx.print_color(
                 arrow_pos.x,
                 arrow_pos.y,
                 RGB::named(rltk::WHITE),
                 RGB::named(rltk::GREY),
-                &"<-".to_string(),
+                "<-",
             );
         }
     }
 }
+
+/// How many lines of history fit on the screen at once, and how far Page Up/Down scroll.
+const HISTORY_LINES: usize = 46;
+/// The turn number is printed in front of every entry, and the text wraps to the right of it.
+const HISTORY_TURN_WIDTH: usize = 6;
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MessageLogResult {
+    Open {
+        scroll: usize,
+        filter: Option<LogCategory>,
+    },
+    Close,
+}
+
+fn filter_name(filter: Option<LogCategory>) -> &'static str {
+    match filter {
+        None => "All",
+        Some(LogCategory::Combat) => "Combat",
+        Some(LogCategory::System) => "System",
+        Some(LogCategory::Loot) => "Loot",
+    }
+}
+
+/// Draws the whole message history, newest at the bottom, with the turn each entry happened on.
+/// The arrow keys and Page Up/Down scroll it, Tab changes which messages are shown, and Escape
+/// (or `m` again) closes it.
+pub fn message_log(ecs: &World, ctx: &mut Rltk) -> MessageLogResult {
+    let (scroll, filter) = match *ecs.fetch::<RunState>() {
+        RunState::MessageLog { scroll, filter } => (scroll, filter),
+        _ => (0, None),
+    };
+
+    let lines = ecs
+        .fetch::<GameLog>()
+        .history(filter, LOG_WIDTH - HISTORY_TURN_WIDTH);
+    let scroll = scroll.min(lines.len().saturating_sub(HISTORY_LINES));
+
+    ctx.draw_box(
+        0,
+        0,
+        79,
+        49,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    let title = format!(" Message history: {} ", filter_name(filter));
+    ctx.print_color(
+        2,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        &title,
+    );
+    ctx.print_color(
+        2,
+        49,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        " Up/Down, PgUp/PgDn: scroll  Tab: filter  Escape: close ",
+    );
+
+    let last = lines.len() - scroll;
+    let first = last.saturating_sub(HISTORY_LINES);
+    for (i, (turn, line)) in lines[first..last].iter().enumerate() {
+        let y = 2 + i as i32;
+        if let Some(turn) = turn {
+            let turn = format!("{:>5}", turn);
+            ctx.print_color(2, y, RGB::named(rltk::GREY), RGB::named(rltk::BLACK), &turn);
+        }
+        print_spans(ctx, 2 + HISTORY_TURN_WIDTH as i32, y, line);
+    }
+
+    let action = match ctx.key {
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::M) => return MessageLogResult::Close,
+        Some(VirtualKeyCode::Up) => HistoryAction::Older,
+        Some(VirtualKeyCode::Down) => HistoryAction::Newer,
+        Some(VirtualKeyCode::PageUp) => HistoryAction::OlderPage,
+        Some(VirtualKeyCode::PageDown) => HistoryAction::NewerPage,
+        Some(VirtualKeyCode::Home) => HistoryAction::Oldest,
+        Some(VirtualKeyCode::End) => HistoryAction::Newest,
+        Some(VirtualKeyCode::Tab) => HistoryAction::NextFilter,
+        _ => return MessageLogResult::Open { scroll, filter },
+    };
+    let (scroll, filter) = scroll_history(action, scroll, filter, lines.len(), HISTORY_LINES);
+    MessageLogResult::Open { scroll, filter }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum InventoryResult {
+    NoResponse,
+    Cancel,
+    Selected { item: Entity },
+}
+
+/// The items the player is carrying, in the order the inventory lists them.
+fn carried_items(ecs: &World) -> Vec<Entity> {
+    let player_entity = ecs.fetch::<Entity>();
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    (&entities, &backpack)
+        .join()
+        .filter(|(_, carried)| carried.owner == *player_entity)
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+/// Draws a box over the map with a title and a footer, big enough for `lines` lines of text.
+/// Returns the row the first line goes on.
+fn draw_menu_box(ctx: &mut Rltk, title: &str, footer: &str, lines: usize) -> i32 {
+    let top = 8;
+    let height = lines.max(1) as i32 + 3;
+    ctx.draw_box(
+        15,
+        top,
+        50,
+        height,
+        RGB::named(rltk::WHITE),
+        RGB::named(rltk::BLACK),
+    );
+    ctx.print_color(
+        18,
+        top,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        title,
+    );
+    ctx.print_color(
+        18,
+        top + height,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        footer,
+    );
+    top + 2
+}
+
+/// Lists what the player is carrying, each with a letter to pick it by. Escape closes the list.
+pub fn show_inventory(ecs: &World, ctx: &mut Rltk) -> InventoryResult {
+    let names = ecs.read_storage::<Name>();
+    let renderables = ecs.read_storage::<Renderable>();
+    let items = carried_items(ecs);
+
+    let top = draw_menu_box(ctx, " Inventory ", " Escape to cancel ", items.len());
+    if items.is_empty() {
+        ctx.print_color(
+            17,
+            top,
+            RGB::named(rltk::GREY),
+            RGB::named(rltk::BLACK),
+            "You aren't carrying anything.",
+        );
+    }
+    for (i, item) in items.iter().enumerate() {
+        let y = top + i as i32;
+        let letter = format!("({})", (b'a' + i as u8) as char);
+        ctx.print_color(
+            17,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            &letter,
+        );
+        let fg = renderables
+            .get(*item)
+            .map_or(RGB::named(rltk::WHITE), |renderable| renderable.fg);
+        ctx.print_color(
+            21,
+            y,
+            fg,
+            RGB::named(rltk::BLACK),
+            &names.get(*item).unwrap().name,
+        );
+    }
+
+    match ctx.key {
+        None => InventoryResult::NoResponse,
+        Some(VirtualKeyCode::Escape) => InventoryResult::Cancel,
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection >= 0 && (selection as usize) < items.len() {
+                InventoryResult::Selected {
+                    item: items[selection as usize],
+                }
+            } else {
+                InventoryResult::NoResponse
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum ItemMenuResult {
+    NoResponse,
+    /// Back to the inventory.
+    Cancel,
+    Use,
+    Drop,
+}
+
+/// Shows what an item is, and what can be done with it.
+pub fn item_menu(ecs: &World, ctx: &mut Rltk, item: Entity) -> ItemMenuResult {
+    let names = ecs.read_storage::<Name>();
+    let descriptions = ecs.read_storage::<Description>();
+    let title = format!(" {} ", names.get(item).map_or("", |name| &name.name));
+
+    // The description is wrapped like a log entry, to fit in the box
+    let description = descriptions.get(item).map_or("", |d| &d.text);
+    let lines = LogEntry::new(LogCategory::Loot)
+        .append(description)
+        .wrap(44);
+
+    let y = draw_menu_box(ctx, &title, " Escape to go back ", lines.len() + 3);
+    for (i, line) in lines.iter().enumerate() {
+        print_spans(ctx, 17, y + i as i32, line);
+    }
+    let actions_y = y + lines.len() as i32 + 1;
+    ctx.print_color(
+        17,
+        actions_y,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "(u) Use",
+    );
+    ctx.print_color(
+        17,
+        actions_y + 1,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "(d) Drop",
+    );
+
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => ItemMenuResult::Cancel,
+        Some(VirtualKeyCode::U) => ItemMenuResult::Use,
+        Some(VirtualKeyCode::D) => ItemMenuResult::Drop,
+        _ => ItemMenuResult::NoResponse,
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum SpellbookResult {
+    NoResponse,
+    Cancel,
+    /// `spell` is its place in the player's `KnownSpells`. `range` is set when a target has to be
+    /// picked first; a spell the player can't afford goes straight through, to be refused.
+    Selected {
+        spell: usize,
+        range: Option<i32>,
+    },
+}
+
+/// Lists the spells the player knows, with what they cost and do, each with a letter to pick it
+/// by. Spells the player hasn't the mana for are greyed out. Escape closes the list.
+pub fn show_spellbook(ecs: &World, ctx: &mut Rltk) -> SpellbookResult {
+    let player_entity = ecs.fetch::<Entity>();
+    let known_spells = ecs.read_storage::<KnownSpells>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let spells = known_spells
+        .get(*player_entity)
+        .map_or(&[][..], |known| &known.spells);
+    let mana = combat_stats
+        .get(*player_entity)
+        .map_or(0, |stats| stats.curr_mana);
+
+    // Each spell's description is wrapped underneath its name
+    let descriptions: Vec<Vec<Vec<LogSpan>>> = spells
+        .iter()
+        .map(|spell| {
+            LogEntry::new(LogCategory::Combat)
+                .colour(RGB::named(rltk::GREY))
+                .append(&spell.description)
+                .wrap(40)
+        })
+        .collect();
+    let lines = descriptions.iter().map(|lines| lines.len() + 1).sum();
+
+    let top = draw_menu_box(ctx, " Spellbook ", " Escape to cancel ", lines);
+    if spells.is_empty() {
+        ctx.print_color(
+            17,
+            top,
+            RGB::named(rltk::GREY),
+            RGB::named(rltk::BLACK),
+            "You don't know any spells.",
+        );
+    }
+    let mut y = top;
+    for (i, (spell, description)) in spells.iter().zip(descriptions.iter()).enumerate() {
+        let fg = if spell.mana_cost <= mana {
+            RGB::named(rltk::WHITE)
+        } else {
+            RGB::named(rltk::GREY)
+        };
+        let letter = format!("({})", (b'a' + i as u8) as char);
+        ctx.print_color(
+            17,
+            y,
+            RGB::named(rltk::YELLOW),
+            RGB::named(rltk::BLACK),
+            &letter,
+        );
+        ctx.print_color(21, y, fg, RGB::named(rltk::BLACK), &spell.name);
+        let cost = format!("{:>3} mana", spell.mana_cost);
+        ctx.print_color(54, y, fg, RGB::named(rltk::BLACK), &cost);
+        for line in description.iter() {
+            y += 1;
+            print_spans(ctx, 23, y, line);
+        }
+        y += 1;
+    }
+
+    match ctx.key {
+        None => SpellbookResult::NoResponse,
+        Some(VirtualKeyCode::Escape) => SpellbookResult::Cancel,
+        Some(key) => {
+            let selection = rltk::letter_to_option(key);
+            if selection >= 0 && (selection as usize) < spells.len() {
+                let spell = &spells[selection as usize];
+                SpellbookResult::Selected {
+                    spell: selection as usize,
+                    range: spell.range.filter(|_| spell.mana_cost <= mana),
+                }
+            } else {
+                SpellbookResult::NoResponse
+            }
+        }
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum TargetingResult {
+    Moving { cursor: Point },
+    Cancel,
+    Selected { target: Point },
+}
+
+/// Where the targeting cursor starts: on the nearest monster in range, or on the player if there
+/// is none.
+pub fn default_target(ecs: &World, range: i32) -> Point {
+    let player_pos = *ecs.fetch::<Point>();
+    let in_range = tiles_in_range(ecs, range);
+    let positions = ecs.read_storage::<Position>();
+    let monsters = ecs.read_storage::<Monster>();
+
+    (&positions, &monsters)
+        .join()
+        .map(|(pos, _monster)| Point::new(pos.x, pos.y))
+        .filter(|pos| in_range.contains(pos))
+        .min_by_key(|pos| rltk::DistanceAlg::PythagorasSquared.distance2d(player_pos, *pos) as i32)
+        .unwrap_or(player_pos)
+}
+
+/// Lets the player pick a tile in sight and in range. The tiles they can pick are highlighted.
+/// The cursor moves with the movement keys and Enter picks its tile, or a tile can be clicked.
+/// Escape cancels.
+pub fn ranged_target(ecs: &World, ctx: &mut Rltk, range: i32, cursor: Point) -> TargetingResult {
+    let (origin_x, origin_y) = camera::get_view_origin(ecs);
+    let in_range = tiles_in_range(ecs, range);
+
+    ctx.print_color(
+        1,
+        0,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Pick a target: move, then Enter (or click). Escape to cancel.",
+    );
+    for tile in in_range.iter() {
+        ctx.set_bg(tile.x - origin_x, tile.y - origin_y, RGB::named(rltk::BLUE));
+    }
+    let cursor_colour = if in_range.contains(&cursor) {
+        RGB::named(rltk::CYAN)
+    } else {
+        RGB::named(rltk::RED)
+    };
+    ctx.set_bg(cursor.x - origin_x, cursor.y - origin_y, cursor_colour);
+
+    // Clicking a tile in range picks it straight away
+    let mouse_pos = ctx.mouse_pos();
+    if let Some((x, y)) = camera::screen_to_map(ecs, mouse_pos.0, mouse_pos.1) {
+        let mouse_target = Point::new(x, y);
+        if in_range.contains(&mouse_target) {
+            ctx.set_bg(mouse_pos.0, mouse_pos.1, RGB::named(rltk::CYAN));
+            if ctx.left_click {
+                return TargetingResult::Selected {
+                    target: mouse_target,
+                };
+            }
+        }
+    }
+
+    // The cursor can't leave the part of the map on screen
+    let moved = |delta_x, delta_y| {
+        let (x, y) = camera::clamp_to_view(ecs, cursor.x + delta_x, cursor.y + delta_y);
+        TargetingResult::Moving {
+            cursor: Point::new(x, y),
+        }
+    };
+    match ctx.key {
+        Some(VirtualKeyCode::Escape) => TargetingResult::Cancel,
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::NumpadEnter)
+            if in_range.contains(&cursor) =>
+        {
+            TargetingResult::Selected { target: cursor }
+        }
+        Some(VirtualKeyCode::Left) | Some(VirtualKeyCode::Numpad4) | Some(VirtualKeyCode::H) => {
+            moved(-1, 0)
+        }
+        Some(VirtualKeyCode::Right) | Some(VirtualKeyCode::Numpad6) | Some(VirtualKeyCode::L) => {
+            moved(1, 0)
+        }
+        Some(VirtualKeyCode::Up) | Some(VirtualKeyCode::Numpad8) | Some(VirtualKeyCode::K) => {
+            moved(0, -1)
+        }
+        Some(VirtualKeyCode::Down) | Some(VirtualKeyCode::Numpad2) | Some(VirtualKeyCode::J) => {
+            moved(0, 1)
+        }
+        Some(VirtualKeyCode::Numpad9) | Some(VirtualKeyCode::U) => moved(1, -1),
+        Some(VirtualKeyCode::Numpad7) | Some(VirtualKeyCode::Y) => moved(-1, -1),
+        Some(VirtualKeyCode::Numpad3) | Some(VirtualKeyCode::N) => moved(1, 1),
+        Some(VirtualKeyCode::Numpad1) | Some(VirtualKeyCode::B) => moved(-1, 1),
+        _ => TargetingResult::Moving { cursor },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuSelection {
+    NewGame,
+    LoadGame,
+    Quit,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum MainMenuResult {
+    NoSelection { selected: MainMenuSelection },
+    Selected { selected: MainMenuSelection },
+}
+
+/// Draws the main menu and moves the highlight with the arrow keys. "Continue" is only offered
+/// when there is a saved game.
+pub fn main_menu(ecs: &World, ctx: &mut Rltk) -> MainMenuResult {
+    let save_exists = saveload_system::does_save_exist();
+    let selection = match *ecs.fetch::<RunState>() {
+        RunState::MainMenu { menu_selection } => menu_selection,
+        _ => MainMenuSelection::NewGame,
+    };
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "DWorld",
+    );
+
+    let mut options = vec![(MainMenuSelection::NewGame, "New Game")];
+    if save_exists {
+        options.push((MainMenuSelection::LoadGame, "Continue"));
+    }
+    options.push((MainMenuSelection::Quit, "Quit"));
+
+    for (i, (option, label)) in options.iter().enumerate() {
+        let fg = if *option == selection {
+            RGB::named(rltk::MAGENTA)
+        } else {
+            RGB::named(rltk::WHITE)
+        };
+        ctx.print_color_centered(24 + i as i32, fg, RGB::named(rltk::BLACK), label);
+    }
+
+    // Such as why the saved game couldn't be continued
+    if let Some(entry) = ecs.fetch::<GameLog>().entries().last() {
+        ctx.print_color_centered(
+            30,
+            RGB::named(rltk::RED),
+            RGB::named(rltk::BLACK),
+            entry.text(),
+        );
+    }
+
+    let current = options
+        .iter()
+        .position(|(option, _)| *option == selection)
+        .unwrap_or(0);
+    match ctx.key {
+        Some(VirtualKeyCode::Up) => MainMenuResult::NoSelection {
+            selected: options[(current + options.len() - 1) % options.len()].0,
+        },
+        Some(VirtualKeyCode::Down) => MainMenuResult::NoSelection {
+            selected: options[(current + 1) % options.len()].0,
+        },
+        Some(VirtualKeyCode::Return) => MainMenuResult::Selected {
+            selected: options[current].0,
+        },
+        _ => MainMenuResult::NoSelection {
+            selected: options[current].0,
+        },
+    }
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum GameOverResult {
+    NoSelection,
+    QuitToMenu,
+}
+
+/// Tells the player how they died. Any key goes back to the main menu.
+pub fn game_over(ecs: &World, ctx: &mut Rltk) -> GameOverResult {
+    let player_entity = ecs.fetch::<Entity>();
+    let killed_by = ecs.read_storage::<KilledBy>();
+    let depth = ecs.fetch::<Map>().depth;
+
+    let cause = match killed_by.get(*player_entity) {
+        Some(killer) => format!("You were killed by {} on depth {}.", killer.name, depth),
+        None => format!("You died on depth {}.", depth),
+    };
+
+    ctx.print_color_centered(
+        15,
+        RGB::named(rltk::YELLOW),
+        RGB::named(rltk::BLACK),
+        "Your journey has ended!",
+    );
+    ctx.print_color_centered(17, RGB::named(rltk::WHITE), RGB::named(rltk::BLACK), &cause);
+    ctx.print_color_centered(
+        20,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        "Press any key to return to the menu.",
+    );
+
+    match ctx.key {
+        None => GameOverResult::NoSelection,
+        Some(_) => GameOverResult::QuitToMenu,
+    }
+}
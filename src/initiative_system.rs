@@ -0,0 +1,78 @@
+use super::{Energy, MyTurn, Player, RunState, Slowed};
+use specs::prelude::*;
+
+/// The energy needed to take a turn, and what most actions use up.
+pub const ACTION_COST: i32 = 100;
+
+/// The speed of the player and most monsters: one action every ten ticks.
+pub const NORMAL_SPEED: i32 = 10;
+
+/// Hands out turns. Ticks go by, with everyone gaining energy, until at least one entity has
+/// enough to act; those entities get `MyTurn`. When the player is among them, the game waits for
+/// their input. Slowed entities gain energy at half their speed.
+pub struct InitiativeSystem {}
+
+impl<'a> System<'a> for InitiativeSystem {
+    type SystemData = (
+        Entities<'a>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, Player>,
+        WriteExpect<'a, RunState>,
+        WriteStorage<'a, Slowed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut energies, mut turns, players, mut runstate, mut slowed) = data;
+
+        if *runstate != RunState::Ticking {
+            return;
+        }
+
+        // Turns left unused are passed up
+        turns.clear();
+
+        // Nobody with any speed would mean ticking forever, so hand over to the player
+        if !(&energies).join().any(|energy| energy.speed > 0) {
+            *runstate = RunState::AwaitingInput;
+            return;
+        }
+
+        while turns.is_empty() {
+            for (entity, energy) in (&entities, &mut energies).join() {
+                energy.energy += match slowed.get(entity) {
+                    // Rounded up, so that nobody slows to a standstill
+                    Some(_) => (energy.speed + 1) / 2,
+                    None => energy.speed,
+                };
+                if energy.energy >= ACTION_COST {
+                    turns
+                        .insert(entity, MyTurn {})
+                        .expect("Unable to insert turn");
+                    if let Some(slow) = slowed.get_mut(entity) {
+                        slow.turns -= 1;
+                        if slow.turns < 1 {
+                            slowed.remove(entity);
+                        }
+                    }
+                    if players.get(entity).is_some() {
+                        *runstate = RunState::AwaitingInput;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Uses up an entity's energy after it has acted, ending its turn.
+pub fn spend_turn(
+    energies: &mut WriteStorage<Energy>,
+    turns: &mut WriteStorage<MyTurn>,
+    entity: Entity,
+    cost: i32,
+) {
+    if let Some(energy) = energies.get_mut(entity) {
+        energy.energy -= cost;
+    }
+    turns.remove(entity);
+}
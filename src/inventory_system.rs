@@ -0,0 +1,241 @@
+use super::{
+    gamelog::{GameLog, LogCategory, LogEntry},
+    CombatStats, Consumable, InBackpack, IncreasesMaxHp, InflictsDamage, Map, Name, Position,
+    ProvidesHealing, Renderable, RestoresMana, SufferDamage, WantsToDropItem, WantsToPickupItem,
+    WantsToUseItem,
+};
+use rltk::RGB;
+use specs::prelude::*;
+
+/// Moves the items someone wants to pick up off the floor and into their backpack.
+pub struct ItemCollectionSystem {}
+
+impl<'a> System<'a> for ItemCollectionSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        WriteStorage<'a, WantsToPickupItem>,
+        WriteStorage<'a, Position>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            mut wants_pickup,
+            mut positions,
+            names,
+            mut backpack,
+            renderables,
+        ) = data;
+
+        for pickup in wants_pickup.join() {
+            positions.remove(pickup.item);
+            backpack
+                .insert(
+                    pickup.item,
+                    InBackpack {
+                        owner: pickup.collected_by,
+                    },
+                )
+                .expect("Unable to insert backpack entry");
+
+            if pickup.collected_by == *player_entity {
+                let colour = renderables
+                    .get(pickup.item)
+                    .map_or(RGB::named(rltk::WHITE), |renderable| renderable.fg);
+                gamelog.add(
+                    LogEntry::new(LogCategory::Loot)
+                        .append("You pick up the ")
+                        .colour(colour)
+                        .append(&names.get(pickup.item).unwrap().name)
+                        .colour(RGB::named(rltk::WHITE))
+                        .append("."),
+                );
+            }
+        }
+
+        wants_pickup.clear();
+    }
+}
+
+/// Takes the items someone wants to drop out of their backpack, and puts them on the floor where
+/// they stand.
+pub struct ItemDropSystem {}
+
+impl<'a> System<'a> for ItemDropSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToDropItem>,
+        ReadStorage<'a, Name>,
+        WriteStorage<'a, Position>,
+        WriteStorage<'a, InBackpack>,
+        ReadStorage<'a, Renderable>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            entities,
+            mut wants_drop,
+            names,
+            mut positions,
+            mut backpack,
+            renderables,
+        ) = data;
+
+        for (entity, to_drop) in (&entities, &wants_drop).join() {
+            let dropper_pos = match positions.get(entity) {
+                Some(pos) => pos.clone(),
+                None => continue,
+            };
+            positions
+                .insert(to_drop.item, dropper_pos)
+                .expect("Unable to insert position");
+            backpack.remove(to_drop.item);
+
+            if entity == *player_entity {
+                let colour = renderables
+                    .get(to_drop.item)
+                    .map_or(RGB::named(rltk::WHITE), |renderable| renderable.fg);
+                gamelog.add(
+                    LogEntry::new(LogCategory::Loot)
+                        .append("You drop the ")
+                        .colour(colour)
+                        .append(&names.get(to_drop.item).unwrap().name)
+                        .colour(RGB::named(rltk::WHITE))
+                        .append("."),
+                );
+            }
+        }
+
+        wants_drop.clear();
+    }
+}
+
+/// Applies the effects of the items being used, and uses up consumables. Items are used on
+/// whoever uses them, or on everything on the target tile of a ranged item. Hit points and mana
+/// never go over their maximums.
+pub struct ItemUseSystem {}
+
+impl<'a> System<'a> for ItemUseSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToUseItem>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Renderable>,
+        ReadStorage<'a, Consumable>,
+        ReadStorage<'a, ProvidesHealing>,
+        ReadStorage<'a, RestoresMana>,
+        ReadStorage<'a, IncreasesMaxHp>,
+        ReadStorage<'a, InflictsDamage>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            map,
+            entities,
+            mut wants_use,
+            names,
+            renderables,
+            consumables,
+            healing,
+            restores_mana,
+            increases_max_hp,
+            inflicts_damage,
+            mut combat_stats,
+            mut suffer_damage,
+        ) = data;
+
+        for (entity, useitem) in (&entities, &wants_use).join() {
+            let targets: Vec<Entity> = match useitem.target {
+                None => vec![entity],
+                Some(target) => map.tile_content[map.xy_idx(target.x, target.y)]
+                    .iter()
+                    .filter(|target| combat_stats.contains(**target))
+                    .copied()
+                    .collect(),
+            };
+
+            let mut effects: Vec<String> = Vec::new();
+            let mut hits: Vec<LogEntry> = Vec::new();
+            for target in targets.iter() {
+                if let Some(stats) = combat_stats.get_mut(*target) {
+                    // A bigger maximum first, so healing from the same item can fill it
+                    if let Some(increase) = increases_max_hp.get(useitem.item) {
+                        stats.max_hp += increase.amount;
+                        stats.curr_hp += increase.amount;
+                        effects.push(format!("raising max HP by {}", increase.amount));
+                    }
+                    if let Some(heal) = healing.get(useitem.item) {
+                        let before = stats.curr_hp;
+                        stats.curr_hp = i32::min(stats.max_hp, stats.curr_hp + heal.heal_amount);
+                        effects.push(format!("healing {} hp", stats.curr_hp - before));
+                    }
+                    if let Some(mana) = restores_mana.get(useitem.item) {
+                        let before = stats.curr_mana;
+                        stats.curr_mana =
+                            i32::min(stats.max_mana, stats.curr_mana + mana.mana_amount);
+                        effects.push(format!("restoring {} mana", stats.curr_mana - before));
+                    }
+
+                    if let Some(damage) = inflicts_damage.get(useitem.item) {
+                        SufferDamage::new_damage(
+                            &mut suffer_damage,
+                            *target,
+                            damage.damage,
+                            names.get(entity).map_or("something", |name| &name.name),
+                        );
+                        hits.push(
+                            LogEntry::new(LogCategory::Combat)
+                                .name_of(useitem.item, &names, &renderables)
+                                .append(" hits ")
+                                .name_of(*target, &names, &renderables)
+                                .damage(damage.damage),
+                        );
+                    }
+                }
+            }
+
+            if entity == *player_entity {
+                gamelog.add(
+                    LogEntry::new(LogCategory::Loot)
+                        .append("You use the ")
+                        .name_of(useitem.item, &names, &renderables)
+                        .used_on(
+                            entity,
+                            targets.first().copied(),
+                            &effects,
+                            &names,
+                            &renderables,
+                        ),
+                );
+            }
+            for hit in hits {
+                gamelog.add(hit);
+            }
+
+            if consumables.get(useitem.item).is_some() {
+                entities.delete(useitem.item).expect("Delete failed");
+            }
+        }
+
+        wants_use.clear();
+    }
+}
@@ -0,0 +1,609 @@
+//! The game itself: the ECS World, the run state machine and the systems that drive it.
+//!
+//! `State` can be played without a window, by sending it `PlayerCommand`s and stepping it along
+//! (see `State::submit` and `State::run_until_input`). The rltk window only adds keyboard input
+//! and drawing on top, in `GameState::tick`.
+
+use rltk::{GameState, Point, RandomNumberGenerator, Rltk};
+use specs::prelude::*;
+use specs::saveload::{SimpleMarker, SimpleMarkerAllocator};
+use std::collections::HashSet;
+
+mod components;
+pub use components::*;
+
+mod map;
+pub use map::*;
+
+mod player;
+pub use player::*;
+
+mod rect;
+pub use rect::Rect;
+
+mod initiative_system;
+pub use initiative_system::{InitiativeSystem, ACTION_COST, NORMAL_SPEED};
+
+mod visibility_system;
+pub use visibility_system::VisibilitySystem;
+
+mod monster_ai_system;
+pub use monster_ai_system::MonsterAI;
+
+mod map_indexing_system;
+pub use map_indexing_system::MapIndexingSystem;
+
+mod melee_combat_system;
+pub use melee_combat_system::MeleeCombatSystem;
+
+mod damage_system;
+pub use damage_system::{delete_the_dead, DamageSystem};
+
+mod inventory_system;
+pub use inventory_system::{ItemCollectionSystem, ItemDropSystem, ItemUseSystem};
+
+mod spell_system;
+pub use spell_system::SpellCastSystem;
+
+mod gui;
+
+mod spawner;
+
+mod random_table;
+
+mod raws;
+
+mod resources;
+
+mod map_builders;
+pub use map_builders::{builder_names, check_map_size, MapGenSettings};
+
+mod camera;
+pub use camera::{clamp_to_view, VIEW_HEIGHT, VIEW_WIDTH};
+
+mod saveload_system;
+
+mod gamelog;
+pub use gamelog::{
+    scroll_history, GameLog, HistoryAction, HistoryLine, LogCategory, LogEntry, LogSpan,
+    MAX_LOG_ENTRIES,
+};
+
+#[cfg(feature = "wizard")]
+mod wizard;
+
+/// The seed used to create the game's `RandomNumberGenerator` resource.
+/// Starting a game with the same seed and inputs always plays out the same way.
+pub struct GameSeed {
+    pub seed: u64,
+}
+
+pub struct State {
+    pub ecs: World,
+    mapgen_settings: MapGenSettings,
+    /// `None` picks a new random seed for every game.
+    seed: Option<u64>,
+}
+
+#[derive(PartialEq, Copy, Clone)]
+pub enum RunState {
+    AwaitingInput,
+    PreRun,
+    /// Time passes until someone is ready to act. The systems keep running until it is the
+    /// player's turn again.
+    Ticking,
+    NextLevel,
+    SaveGame,
+    LoadGame,
+    MainMenu {
+        menu_selection: gui::MainMenuSelection,
+    },
+    GameOver,
+    /// The full message history. `scroll` is how many lines up from the newest it has been
+    /// scrolled, and `filter` the only category shown, if any.
+    MessageLog {
+        scroll: usize,
+        filter: Option<LogCategory>,
+    },
+    /// The list of carried items.
+    ShowInventory,
+    /// What can be done with an item picked from the inventory.
+    ItemMenu {
+        item: Entity,
+    },
+    /// Picking a tile up to `range` away, with a cursor that starts at `cursor`.
+    ShowTargeting {
+        range: i32,
+        cursor: Point,
+        purpose: TargetingPurpose,
+    },
+    /// The list of known spells.
+    ShowSpellbook,
+    /// Typing a command into the wizard console.
+    #[cfg(feature = "wizard")]
+    WizardConsole,
+}
+
+/// What the player is picking a target for.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum TargetingPurpose {
+    UseItem {
+        item: Entity,
+    },
+    /// `spell` is its place in the player's `KnownSpells`.
+    CastSpell {
+        spell: usize,
+    },
+}
+
+/// Registers every component with a World.
+pub fn register_components(ecs: &mut World) {
+    ecs.register::<Position>();
+    ecs.register::<Renderable>();
+    ecs.register::<Player>();
+    ecs.register::<Viewshed>();
+    ecs.register::<Monster>();
+    ecs.register::<Name>();
+    ecs.register::<BlocksTile>();
+    ecs.register::<CombatStats>();
+    ecs.register::<WantsToMelee>();
+    ecs.register::<SufferDamage>();
+    ecs.register::<KilledBy>();
+    ecs.register::<Energy>();
+    ecs.register::<MyTurn>();
+    ecs.register::<Invulnerable>();
+    ecs.register::<Item>();
+    ecs.register::<InBackpack>();
+    ecs.register::<WantsToPickupItem>();
+    ecs.register::<WantsToDropItem>();
+    ecs.register::<Description>();
+    ecs.register::<WantsToUseItem>();
+    ecs.register::<Consumable>();
+    ecs.register::<ProvidesHealing>();
+    ecs.register::<RestoresMana>();
+    ecs.register::<IncreasesMaxHp>();
+    ecs.register::<Ranged>();
+    ecs.register::<InflictsDamage>();
+    ecs.register::<KnownSpells>();
+    ecs.register::<WantsToCastSpell>();
+    ecs.register::<Slowed>();
+    ecs.register::<SimpleMarker<SerializeMe>>();
+    ecs.register::<SerializationHelper>();
+}
+
+/// Runs every system once, in order, then applies the changes they made to the World.
+pub fn run_systems(ecs: &mut World) {
+    // Items are picked up, dropped and used, and spells cast, before anyone else gets to act, so
+    // a monster hit from afar doesn't get to step out of the way first
+    let mut pickup = ItemCollectionSystem {};
+    pickup.run_now(ecs);
+    let mut drop_items = ItemDropSystem {};
+    drop_items.run_now(ecs);
+    let mut use_items = ItemUseSystem {};
+    use_items.run_now(ecs);
+    let mut cast_spells = SpellCastSystem {};
+    cast_spells.run_now(ecs);
+    let mut damage = DamageSystem {};
+    damage.run_now(ecs);
+
+    let mut initiative = InitiativeSystem {};
+    initiative.run_now(ecs);
+    let mut vis = VisibilitySystem {};
+    vis.run_now(ecs);
+    let mut mob = MonsterAI {};
+    mob.run_now(ecs);
+    let mut mapindex = MapIndexingSystem {};
+    mapindex.run_now(ecs);
+    let mut melee = MeleeCombatSystem {};
+    melee.run_now(ecs);
+    damage.run_now(ecs);
+
+    ecs.maintain();
+}
+
+/// Keeps the game going until it is the player's turn, or the game is over. Returns the new
+/// run state, which is also stored in the World.
+pub fn run_ticks(ecs: &mut World) -> RunState {
+    *ecs.write_resource::<RunState>() = RunState::Ticking;
+    loop {
+        run_systems(ecs);
+        delete_the_dead(ecs);
+        let runstate = *ecs.fetch::<RunState>();
+        if runstate != RunState::Ticking {
+            return runstate;
+        }
+    }
+}
+
+impl State {
+    /// Makes a game that starts on the main menu. Call `new_game` to skip straight to playing.
+    pub fn new(mapgen_settings: MapGenSettings, seed: Option<u64>) -> State {
+        let mut gs = State {
+            ecs: World::new(),
+            mapgen_settings,
+            seed,
+        };
+        gs.reset_world();
+        map_builders::check_prefabs(&gs.ecs.fetch::<raws::RawMaster>());
+        gs
+    }
+
+    /// Replaces the whole World with an empty one, ready for a new or loaded game.
+    fn reset_world(&mut self) {
+        self.ecs = World::new();
+
+        register_components(&mut self.ecs);
+
+        self.ecs.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+        self.ecs.insert(raws::RawMaster::load());
+        self.ecs.insert(GameLog::new());
+        #[cfg(feature = "wizard")]
+        self.ecs.insert(wizard::WizardConsole::default());
+        self.ecs.insert(RunState::MainMenu {
+            menu_selection: gui::MainMenuSelection::NewGame,
+        });
+    }
+
+    /// Starts over from the first level with a new player.
+    pub fn new_game(&mut self) {
+        self.reset_world();
+
+        let seed = self
+            .seed
+            .unwrap_or_else(|| RandomNumberGenerator::new().next_u64());
+        self.ecs.insert(GameSeed { seed });
+        self.ecs.insert(RandomNumberGenerator::seeded(seed));
+
+        let start = self.generate_world_map(1);
+        let player_entity = spawner::player(&mut self.ecs, start.x, start.y);
+
+        // Resources, used by various systems and functions
+
+        self.ecs.insert(RunState::PreRun);
+        self.ecs.insert(player_entity);
+        let mut gamelog = GameLog::new();
+        gamelog.log(LogCategory::System, "Welcome to DWorld!");
+        self.ecs.insert(gamelog);
+    }
+
+    /// Replaces the game with the one in the save file, as the main menu's "Continue" does.
+    /// If the save can't be loaded, the game is left on the main menu, with the reason in the log.
+    pub fn continue_game(&mut self) -> Result<(), String> {
+        self.reset_world();
+        if let Err(error) = saveload_system::load_game(&mut self.ecs) {
+            let message = format!("Unable to load the saved game: {}", error);
+            self.ecs
+                .write_resource::<GameLog>()
+                .log(LogCategory::System, message.clone());
+            return Err(message);
+        }
+        self.set_run_state(RunState::PreRun);
+        Ok(())
+    }
+
+    pub fn run_state(&self) -> RunState {
+        *self.ecs.fetch::<RunState>()
+    }
+
+    fn set_run_state(&mut self, runstate: RunState) {
+        *self.ecs.write_resource::<RunState>() = runstate;
+    }
+
+    /// True while nothing can happen until the player acts: on their turn, and in the menus.
+    pub fn is_waiting_for_player(&self) -> bool {
+        match self.run_state() {
+            RunState::AwaitingInput
+            | RunState::MainMenu { .. }
+            | RunState::GameOver
+            | RunState::MessageLog { .. }
+            | RunState::ShowInventory
+            | RunState::ItemMenu { .. }
+            | RunState::ShowTargeting { .. }
+            | RunState::ShowSpellbook => true,
+            #[cfg(feature = "wizard")]
+            RunState::WizardConsole => true,
+            _ => false,
+        }
+    }
+
+    /// Makes the player carry out `command`. Ignored unless it is the player's turn.
+    pub fn submit(&mut self, command: PlayerCommand) {
+        if self.run_state() == RunState::AwaitingInput {
+            let newrunstate = handle_command(&mut self.ecs, command);
+            self.set_run_state(newrunstate);
+        }
+    }
+
+    /// Moves the game on by one state, such as running the monsters' turn. Does nothing while the
+    /// game is waiting for the player.
+    pub fn step(&mut self) {
+        let mut newrunstate = self.run_state();
+
+        match newrunstate {
+            RunState::PreRun => {
+                run_systems(&mut self.ecs);
+                newrunstate = RunState::Ticking;
+            }
+            RunState::Ticking => {
+                newrunstate = run_ticks(&mut self.ecs);
+            }
+            RunState::NextLevel => {
+                self.goto_next_level();
+                newrunstate = RunState::PreRun;
+            }
+            RunState::SaveGame => {
+                let result = saveload_system::save_game(&mut self.ecs);
+                let mut gamelog = self.ecs.write_resource::<GameLog>();
+                match result {
+                    Ok(()) => gamelog.log(LogCategory::System, "Game saved."),
+                    Err(error) => gamelog.log(
+                        LogCategory::System,
+                        format!("Unable to save the game: {}", error),
+                    ),
+                }
+                newrunstate = RunState::AwaitingInput;
+            }
+            RunState::LoadGame => {
+                if saveload_system::does_save_exist() {
+                    // A save that can't be loaded leaves the current game as it was
+                    newrunstate = match saveload_system::load_game(&mut self.ecs) {
+                        Ok(()) => RunState::PreRun,
+                        Err(error) => {
+                            let mut gamelog = self.ecs.write_resource::<GameLog>();
+                            gamelog.log(
+                                LogCategory::System,
+                                format!("Unable to load the saved game: {}", error),
+                            );
+                            RunState::AwaitingInput
+                        }
+                    };
+                } else {
+                    let mut gamelog = self.ecs.write_resource::<GameLog>();
+                    gamelog.log(LogCategory::System, "There is no saved game.");
+                    newrunstate = RunState::AwaitingInput;
+                }
+            }
+            RunState::AwaitingInput
+            | RunState::MainMenu { .. }
+            | RunState::GameOver
+            | RunState::MessageLog { .. }
+            | RunState::ShowInventory
+            | RunState::ItemMenu { .. }
+            | RunState::ShowTargeting { .. }
+            | RunState::ShowSpellbook => {}
+            #[cfg(feature = "wizard")]
+            RunState::WizardConsole => {}
+        }
+
+        self.set_run_state(newrunstate);
+        delete_the_dead(&mut self.ecs);
+    }
+
+    /// Steps the game along until it needs the player again.
+    pub fn run_until_input(&mut self) {
+        while !self.is_waiting_for_player() {
+            self.step();
+        }
+    }
+
+    /// Everything except the player, and what they carry, is left behind when changing levels.
+    fn entities_to_remove_on_level_change(&mut self) -> Vec<Entity> {
+        let entities = self.ecs.entities();
+        let player_entity = self.ecs.fetch::<Entity>();
+        let backpack = self.ecs.read_storage::<InBackpack>();
+
+        entities
+            .join()
+            .filter(|entity| *entity != *player_entity)
+            .filter(|entity| {
+                backpack
+                    .get(*entity)
+                    .is_none_or(|carried| carried.owner != *player_entity)
+            })
+            .collect()
+    }
+
+    /// Builds a map for the given depth, inserts it into the World and fills it with monsters.
+    /// Returns where the player should start, which is also stored as the `Point` resource.
+    fn generate_world_map(&mut self, new_depth: i32) -> Position {
+        let level = {
+            let raws = self.ecs.fetch::<raws::RawMaster>();
+            let mut rng = self.ecs.write_resource::<RandomNumberGenerator>();
+            map_builders::generate_level(&self.mapgen_settings, new_depth, &raws, &mut rng)
+        };
+
+        let start = level.starting_position;
+        let mut occupied = HashSet::new();
+        occupied.insert(level.map.xy_idx(start.x, start.y));
+
+        self.ecs.insert(level.map);
+        for (idx, name) in level.spawn_list.iter() {
+            spawner::spawn_named(&mut self.ecs, name, *idx);
+            occupied.insert(*idx);
+        }
+        for region in level.spawn_regions.iter() {
+            spawner::spawn_region(&mut self.ecs, region, &mut occupied);
+        }
+
+        self.ecs.insert(Point::new(start.x, start.y));
+        start
+    }
+
+    fn goto_next_level(&mut self) {
+        let current_depth = self.ecs.fetch::<Map>().depth;
+        self.change_level(current_depth + 1);
+
+        let mut gamelog = self.ecs.write_resource::<GameLog>();
+        gamelog.log(LogCategory::System, "You descend to the next level.");
+    }
+
+    /// Replaces the level with a newly generated one at `new_depth`, taking only the player along.
+    fn change_level(&mut self, new_depth: i32) {
+        let to_delete = self.entities_to_remove_on_level_change();
+        for target in to_delete {
+            self.ecs
+                .delete_entity(target)
+                .expect("Unable to delete entity");
+        }
+
+        // Build a new map and move the player to its start
+        let start = self.generate_world_map(new_depth);
+
+        let player_entity = self.ecs.fetch::<Entity>();
+        let mut positions = self.ecs.write_storage::<Position>();
+        if let Some(player_pos) = positions.get_mut(*player_entity) {
+            *player_pos = start;
+        }
+
+        // The old field of view refers to the previous map
+        let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+        if let Some(vs) = viewsheds.get_mut(*player_entity) {
+            vs.dirty = true;
+        }
+    }
+}
+
+impl GameState for State {
+    fn tick(&mut self, ctx: &mut Rltk) {
+        ctx.cls();
+
+        // The menus have the screen to themselves, and may not have a map to show anyway. Other
+        // menus are drawn over the map.
+        match self.run_state() {
+            RunState::MainMenu { .. } | RunState::GameOver | RunState::MessageLog { .. } => {}
+            _ => {
+                camera::render_camera(&self.ecs, ctx);
+                gui::draw_ui(&self.ecs, ctx);
+            }
+        }
+
+        match self.run_state() {
+            RunState::MainMenu { .. } => match gui::main_menu(&self.ecs, ctx) {
+                gui::MainMenuResult::NoSelection { selected } => {
+                    self.set_run_state(RunState::MainMenu {
+                        menu_selection: selected,
+                    });
+                }
+                gui::MainMenuResult::Selected { selected } => match selected {
+                    gui::MainMenuSelection::NewGame => self.new_game(),
+                    // Why it failed is shown on the menu
+                    gui::MainMenuSelection::LoadGame => {
+                        let _ = self.continue_game();
+                    }
+                    gui::MainMenuSelection::Quit => {
+                        ::std::process::exit(0);
+                    }
+                },
+            },
+            RunState::GameOver => {
+                if gui::game_over(&self.ecs, ctx) == gui::GameOverResult::QuitToMenu {
+                    // A fresh World starts out on the main menu
+                    self.reset_world();
+                }
+            }
+            RunState::MessageLog { .. } => match gui::message_log(&self.ecs, ctx) {
+                gui::MessageLogResult::Open { scroll, filter } => {
+                    self.set_run_state(RunState::MessageLog { scroll, filter });
+                }
+                // Reading the log takes no time, so it is still the player's turn
+                gui::MessageLogResult::Close => self.set_run_state(RunState::AwaitingInput),
+            },
+            // Choosing an item takes no time. Only using or dropping it does
+            RunState::ShowInventory => match gui::show_inventory(&self.ecs, ctx) {
+                gui::InventoryResult::NoResponse => {}
+                gui::InventoryResult::Cancel => self.set_run_state(RunState::AwaitingInput),
+                gui::InventoryResult::Selected { item } => {
+                    self.set_run_state(RunState::ItemMenu { item });
+                }
+            },
+            RunState::ItemMenu { item } => match gui::item_menu(&self.ecs, ctx, item) {
+                gui::ItemMenuResult::NoResponse => {}
+                gui::ItemMenuResult::Cancel => self.set_run_state(RunState::ShowInventory),
+                gui::ItemMenuResult::Use => {
+                    let range = self.ecs.read_storage::<Ranged>().get(item).map(|r| r.range);
+                    match range {
+                        // Ranged items need a target first
+                        Some(range) => self.set_run_state(RunState::ShowTargeting {
+                            range,
+                            cursor: gui::default_target(&self.ecs, range),
+                            purpose: TargetingPurpose::UseItem { item },
+                        }),
+                        None => {
+                            self.set_run_state(RunState::AwaitingInput);
+                            self.submit(PlayerCommand::UseItem { item, target: None });
+                        }
+                    }
+                }
+                gui::ItemMenuResult::Drop => {
+                    self.set_run_state(RunState::AwaitingInput);
+                    self.submit(PlayerCommand::DropItem { item });
+                }
+            },
+            RunState::ShowTargeting {
+                range,
+                cursor,
+                purpose,
+            } => match gui::ranged_target(&self.ecs, ctx, range, cursor) {
+                gui::TargetingResult::Moving { cursor } => {
+                    self.set_run_state(RunState::ShowTargeting {
+                        range,
+                        cursor,
+                        purpose,
+                    });
+                }
+                gui::TargetingResult::Cancel => self.set_run_state(RunState::AwaitingInput),
+                gui::TargetingResult::Selected { target } => {
+                    self.set_run_state(RunState::AwaitingInput);
+                    match purpose {
+                        TargetingPurpose::UseItem { item } => self.submit(PlayerCommand::UseItem {
+                            item,
+                            target: Some(target),
+                        }),
+                        TargetingPurpose::CastSpell { spell } => {
+                            self.submit(PlayerCommand::CastSpell {
+                                spell,
+                                target: Some(target),
+                            })
+                        }
+                    }
+                }
+            },
+            // Like the inventory, choosing a spell takes no time. Only casting it does
+            RunState::ShowSpellbook => match gui::show_spellbook(&self.ecs, ctx) {
+                gui::SpellbookResult::NoResponse => {}
+                gui::SpellbookResult::Cancel => self.set_run_state(RunState::AwaitingInput),
+                gui::SpellbookResult::Selected { spell, range } => match range {
+                    // Spells with a range need a target first
+                    Some(range) => self.set_run_state(RunState::ShowTargeting {
+                        range,
+                        cursor: gui::default_target(&self.ecs, range),
+                        purpose: TargetingPurpose::CastSpell { spell },
+                    }),
+                    None => {
+                        self.set_run_state(RunState::AwaitingInput);
+                        self.submit(PlayerCommand::CastSpell {
+                            spell,
+                            target: None,
+                        });
+                    }
+                },
+            },
+            #[cfg(feature = "wizard")]
+            RunState::WizardConsole => match wizard::console_input(&self.ecs, ctx) {
+                wizard::ConsoleResult::Typing => wizard::draw_console(&self.ecs, ctx),
+                wizard::ConsoleResult::Cancel => self.set_run_state(RunState::AwaitingInput),
+                wizard::ConsoleResult::Run { line } => {
+                    self.set_run_state(RunState::AwaitingInput);
+                    // Mistakes are reported in the log, so the result isn't needed here
+                    let _ = self.wizard(&line);
+                }
+            },
+            RunState::AwaitingInput => {
+                if let Some(command) = player_input(ctx) {
+                    self.submit(command);
+                }
+            }
+            _ => self.step(),
+        }
+    }
+}
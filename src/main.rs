@@ -1,122 +1,73 @@
-use rltk::{GameState, Point, Rltk, RGB};
-use specs::prelude::*;
-
-mod components;
-pub use components::*;
-
-mod map;
-pub use map::*;
-
-mod player;
-pub use player::*;
-
-mod rect;
-pub use rect::Rect;
-
-mod visibility_system;
-pub use visibility_system::VisibilitySystem;
-
-mod monster_ai_system;
-pub use monster_ai_system::MonsterAI;
-
-mod map_indexing_system;
-pub use map_indexing_system::MapIndexingSystem;
-
-mod melee_combat_system;
-pub use melee_combat_system::MeleeCombatSystem;
-
-mod damage_system;
-pub use damage_system::DamageSystem;
-
-mod gui;
-
-mod gamelog;
-pub use gamelog::GameLog;
-
-pub struct State {
-    ecs: World,
+use dworld_rust::{builder_names, check_map_size, MapGenSettings, State, VIEW_HEIGHT, VIEW_WIDTH};
+
+/// Returns the value following `flag` on the command line, if present.
+fn arg_value(flag: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
 }
 
-#[derive(PartialEq, Copy, Clone)]
-pub enum RunState {
-    AwaitingInput,
-    PreRun,
-    PlayerTurn,
-    MonsterTurn,
+/// Reads the seed from `--seed <number>` on the command line. Without it, every game gets a
+/// random seed.
+fn seed_from_args() -> Option<u64> {
+    arg_value("--seed").map(|seed| {
+        seed.parse::<u64>()
+            .unwrap_or_else(|_| exit_with_error("--seed must be a positive integer"))
+    })
 }
 
-impl State {
-    fn run_systems(&mut self) {
-        let mut vis = VisibilitySystem {};
-        vis.run_now(&self.ecs);
-        let mut mob = MonsterAI {};
-        mob.run_now(&self.ecs);
-        let mut mapindex = MapIndexingSystem {};
-        mapindex.run_now(&self.ecs);
-        let mut melee = MeleeCombatSystem {};
-        melee.run_now(&self.ecs);
-        let mut damage = DamageSystem {};
-        damage.run_now(&self.ecs);
-
-        self.ecs.maintain();
+/// Reads the map builder from `--builder <name>` on the command line. Without it, every level
+/// uses a randomly picked builder.
+fn builder_from_args() -> Option<String> {
+    let name = arg_value("--builder")?;
+
+    let names = builder_names();
+    if !names.contains(&name.as_str()) {
+        exit_with_error(&format!(
+            "Unknown map builder '{}', expected one of: {}",
+            name,
+            names.join(", ")
+        ));
     }
+    Some(name)
 }
 
-impl GameState for State {
-    fn tick(&mut self, ctx: &mut Rltk) {
-        ctx.cls();
-
-        let mut newrunstate;
-        {
-            let runstate = self.ecs.fetch::<RunState>();
-            newrunstate = *runstate;
-        }
-
-        match newrunstate {
-            RunState::PreRun => {
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
-            }
-            RunState::AwaitingInput => {
-                newrunstate = player_input(self, ctx);
-            }
-            RunState::PlayerTurn => {
-                self.run_systems();
-                newrunstate = RunState::MonsterTurn;
-            }
-            RunState::MonsterTurn => {
-                self.run_systems();
-                newrunstate = RunState::AwaitingInput;
-            }
-        }
-
-        {
-            let mut runwriter = self.ecs.write_resource::<RunState>();
-            *runwriter = newrunstate;
-        }
-
-        damage_system::delete_the_dead(&mut self.ecs);
-
-        draw_map(&self.ecs, ctx);
-
-        let positions = self.ecs.read_storage::<Position>();
-        let renderables = self.ecs.read_storage::<Renderable>();
-        let map = self.ecs.fetch::<Map>();
-
-        for (pos, render) in (&positions, &renderables).join() {
-            let idx = map.xy_idx(pos.x, pos.y);
-            if map.visible_tiles[idx] {
-                ctx.set(pos.x, pos.y, render.fg, render.bg, render.glyph)
+/// Reads the map size from `--map-size <width>x<height>` on the command line.
+/// Without it, maps are exactly as large as the screen.
+fn map_size_from_args() -> (i32, i32) {
+    match arg_value("--map-size") {
+        Some(size) => {
+            let dimensions: Vec<Option<i32>> =
+                size.split('x').map(|d| d.parse::<i32>().ok()).collect();
+            match dimensions[..] {
+                [Some(width), Some(height)] => (width, height),
+                _ => exit_with_error("--map-size must look like 120x80"),
             }
         }
-
-        gui::draw_ui(&self.ecs, ctx);
+        None => (VIEW_WIDTH, VIEW_HEIGHT),
     }
 }
 
+/// Explains what is wrong with the command line, and quits.
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("{}", message);
+    std::process::exit(2);
+}
+
 fn main() -> rltk::BError {
     use rltk::RltkBuilder;
 
+    // Arguments are checked before the window opens, so a bad map size doesn't panic halfway
+    // through a level
+    let seed = seed_from_args();
+    let builder_name = builder_from_args();
+    let (width, height) = map_size_from_args();
+    if let Err(message) = check_map_size(builder_name.as_deref(), width, height) {
+        exit_with_error(&message);
+    }
+
     let context = RltkBuilder::new()
         .with_simple_console(80, 50, "cp437_20x20.png")
         .with_resource_path("resources")
@@ -126,112 +77,14 @@ fn main() -> rltk::BError {
         .with_title("DWorld")
         .build()?;
 
-    let mut gs = State { ecs: World::new() };
-
-    gs.ecs.register::<Position>();
-    gs.ecs.register::<Renderable>();
-    gs.ecs.register::<Player>();
-    gs.ecs.register::<Viewshed>();
-    gs.ecs.register::<Monster>();
-    gs.ecs.register::<Name>();
-    gs.ecs.register::<BlocksTile>();
-    gs.ecs.register::<CombatStats>();
-    gs.ecs.register::<WantsToMelee>();
-    gs.ecs.register::<SufferDamage>();
-
-    let map: Map = Map::new_map_rooms_and_corridors();
-    let (player_x, player_y) = map.rooms[0].center();
-
-    // Create player
-    let player_entity = gs
-        .ecs
-        .create_entity()
-        .with(Position {
-            x: player_x,
-            y: player_y,
-        })
-        .with(Renderable {
-            glyph: rltk::to_cp437('@'),
-            fg: RGB::named(rltk::YELLOW),
-            bg: RGB::named(rltk::BLACK),
-        })
-        .with(Player {})
-        .with(Viewshed {
-            visible_tiles: Vec::new(),
-            range: 8,
-            dirty: true, // Force initial recompute
-        })
-        .with(Name {
-            name: "Player".to_string(),
-        })
-        .with(CombatStats {
-            max_hp: 30,
-            curr_hp: 30,
-            defense: 2,
-            magic_res: 4,
-            max_mana: 50,
-            curr_mana: 50,
-            power: 4,
-        })
-        .build();
-
-    let mut rng = rltk::RandomNumberGenerator::new();
-    for (i, room) in map.rooms.iter().skip(1).enumerate() {
-        let (x, y) = room.center();
-
-        let glyph: rltk::FontCharType;
-        let name: String;
-        let roll = rng.roll_dice(1, 2); // Rolls 1 or 2
-        match roll {
-            1 => {
-                glyph = rltk::to_cp437('g');
-                name = "Goblin".to_string()
-            }
-            _ => {
-                glyph = rltk::to_cp437('o');
-                name = "Orc".to_string()
-            }
-        }
-
-        gs.ecs
-            .create_entity()
-            .with(Position { x, y })
-            .with(Renderable {
-                glyph: glyph,
-                fg: RGB::named(rltk::RED),
-                bg: RGB::named(rltk::BLACK),
-            })
-            .with(Viewshed {
-                visible_tiles: Vec::new(),
-                range: 8,
-                dirty: true,
-            })
-            .with(Monster {})
-            .with(Name {
-                name: format!("{} #{}", &name, i),
-            })
-            .with(CombatStats {
-                max_hp: 5,
-                curr_hp: 5,
-                defense: 2,
-                magic_res: 4,
-                max_mana: 50,
-                curr_mana: 50,
-                power: 3,
-            })
-            .with(BlocksTile {})
-            .build();
-    }
-
-    // Resources, used by various systems and functions
-
-    gs.ecs.insert(RunState::PreRun);
-    gs.ecs.insert(map);
-    gs.ecs.insert(player_entity);
-    gs.ecs.insert(Point::new(player_x, player_y));
-    gs.ecs.insert(GameLog {
-        entries: vec!["Welcome to DWorld!".to_string()],
-    });
+    let gs = State::new(
+        MapGenSettings {
+            builder_name,
+            width,
+            height,
+        },
+        seed,
+    );
 
     rltk::main_loop(context, gs)
 }
@@ -0,0 +1,139 @@
+use super::{
+    apply_horizontal_tunnel, apply_room_to_map, apply_vertical_tunnel, place_doors,
+    room_spawn_regions, Map, MapBuilder, Position, Rect, TileType,
+};
+use rltk::RandomNumberGenerator;
+
+/// Areas narrower or shorter than this are never split further.
+const MIN_LEAF_SIZE: i32 = 8;
+const MIN_ROOM_SIZE: i32 = 4;
+const MAX_ROOM_SIZE: i32 = 12;
+
+/// Binary space partition dungeon: the map is recursively split in two, one room is placed in
+/// every leaf of the resulting tree, and the two halves of every split are joined by a corridor.
+/// Rooms never overlap, so the whole map gets used.
+pub struct BspDungeonBuilder {
+    map: Map,
+}
+
+impl BspDungeonBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> BspDungeonBuilder {
+        BspDungeonBuilder {
+            map: Map::new(new_depth, width, height),
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // The outermost row and column of the map always stay wall
+        let whole_map = Rect {
+            x1: 0,
+            y1: 0,
+            x2: self.map.width - 1,
+            y2: self.map.height - 1,
+        };
+        self.partition(whole_map, 0, rng);
+
+        place_doors(&mut self.map);
+
+        let stairs_position = self.map.rooms[self.map.rooms.len() - 1].center();
+        let stairs_idx = self.map.xy_idx(stairs_position.0, stairs_position.1);
+        self.map.tiles[stairs_idx] = TileType::DownStairs;
+    }
+
+    /// Splits `area` until it is small enough to hold a single room, and joins the two halves
+    /// together. Returns the indices (in `map.rooms`) of every room placed inside `area`.
+    fn partition(&mut self, area: Rect, depth: i32, rng: &mut RandomNumberGenerator) -> Vec<usize> {
+        let width = area.x2 - area.x1;
+        let height = area.y2 - area.y1;
+        let can_split_x = width >= MIN_LEAF_SIZE * 2;
+        let can_split_y = height >= MIN_LEAF_SIZE * 2;
+
+        // Past the first few splits, occasionally stop early so room sizes vary
+        let small_enough = width <= MAX_ROOM_SIZE * 2 && height <= MAX_ROOM_SIZE * 2;
+        let stop_early = depth > 2 && small_enough && rng.roll_dice(1, 4) == 1;
+
+        if (!can_split_x && !can_split_y) || stop_early {
+            return vec![self.add_room(&area, rng)];
+        }
+
+        // Prefer cutting across the longest side, to avoid long thin leaves
+        let split_x = if !can_split_y {
+            true
+        } else if !can_split_x {
+            false
+        } else if width as f32 > height as f32 * 1.25 {
+            true
+        } else if height as f32 > width as f32 * 1.25 {
+            false
+        } else {
+            rng.range(0, 2) == 1
+        };
+
+        let (first, second) = if split_x {
+            let cut = area.x1 + rng.range(MIN_LEAF_SIZE, width - MIN_LEAF_SIZE + 1);
+            (Rect { x2: cut, ..area }, Rect { x1: cut, ..area })
+        } else {
+            let cut = area.y1 + rng.range(MIN_LEAF_SIZE, height - MIN_LEAF_SIZE + 1);
+            (Rect { y2: cut, ..area }, Rect { y1: cut, ..area })
+        };
+
+        let mut rooms = self.partition(first, depth + 1, rng);
+        let second_rooms = self.partition(second, depth + 1, rng);
+
+        // Connect the siblings through a random room on each side
+        let from = rooms[rng.random_slice_index(&rooms).unwrap()];
+        let to = second_rooms[rng.random_slice_index(&second_rooms).unwrap()];
+        self.connect_rooms(from, to, rng);
+
+        rooms.extend(second_rooms);
+        rooms
+    }
+
+    /// Places a randomly sized room inside the leaf, leaving at least one wall on its far sides
+    /// so neighbouring rooms never touch.
+    fn add_room(&mut self, leaf: &Rect, rng: &mut RandomNumberGenerator) -> usize {
+        let leaf_width = leaf.x2 - leaf.x1;
+        let leaf_height = leaf.y2 - leaf.y1;
+
+        let w = rng.range(MIN_ROOM_SIZE, i32::min(leaf_width, MAX_ROOM_SIZE));
+        let h = rng.range(MIN_ROOM_SIZE, i32::min(leaf_height, MAX_ROOM_SIZE));
+        let x = leaf.x1 + rng.range(0, leaf_width - w);
+        let y = leaf.y1 + rng.range(0, leaf_height - h);
+
+        let room = Rect::new(x, y, w, h);
+        apply_room_to_map(&mut self.map, &room);
+        self.map.rooms.push(room);
+        self.map.rooms.len() - 1
+    }
+
+    fn connect_rooms(&mut self, from: usize, to: usize, rng: &mut RandomNumberGenerator) {
+        let (from_x, from_y) = self.map.rooms[from].center();
+        let (to_x, to_y) = self.map.rooms[to].center();
+        if rng.range(0, 2) == 1 {
+            apply_horizontal_tunnel(&mut self.map, from_x, to_x, from_y);
+            apply_vertical_tunnel(&mut self.map, from_y, to_y, to_x);
+        } else {
+            apply_vertical_tunnel(&mut self.map, from_y, to_y, from_x);
+            apply_horizontal_tunnel(&mut self.map, from_x, to_x, to_y);
+        }
+    }
+}
+
+impl MapBuilder for BspDungeonBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        let (x, y) = self.map.rooms[0].center();
+        Position { x, y }
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        room_spawn_regions(&self.map)
+    }
+}
@@ -0,0 +1,101 @@
+use super::{
+    chunk_spawn_regions, floor_nearest_center, keep_largest_region, place_stairs_furthest_from,
+    Map, MapBuilder, Position, TileType,
+};
+use rltk::RandomNumberGenerator;
+
+const SMOOTHING_ITERATIONS: i32 = 15;
+
+/// Natural looking caves, made by filling the map with random noise and repeatedly smoothing it
+/// out: tiles surrounded by walls become wall, the rest become floor.
+/// See http://www.roguebasin.com/index.php?title=Cellular_Automata_Method_for_Generating_Random_Cave-Like_Levels
+pub struct CellularAutomataBuilder {
+    map: Map,
+    starting_position: Position,
+}
+
+impl CellularAutomataBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32) -> CellularAutomataBuilder {
+        CellularAutomataBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+        }
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Roughly 55% floor to begin with, keeping the outer edge solid
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = if rng.roll_dice(1, 100) > 55 {
+                    TileType::Wall
+                } else {
+                    TileType::Floor
+                };
+            }
+        }
+
+        for _i in 0..SMOOTHING_ITERATIONS {
+            self.smooth();
+        }
+
+        // Isolated pockets of cave can never be reached, so get rid of them
+        keep_largest_region(&mut self.map);
+
+        self.starting_position = floor_nearest_center(&self.map);
+        place_stairs_furthest_from(&mut self.map, &self.starting_position);
+    }
+
+    fn smooth(&mut self) {
+        let mut new_tiles = self.map.tiles.clone();
+        let width = self.map.width as usize;
+
+        for y in 1..self.map.height - 1 {
+            for x in 1..self.map.width - 1 {
+                let idx = self.map.xy_idx(x, y);
+                let neighbours = [
+                    idx - 1,
+                    idx + 1,
+                    idx - width,
+                    idx + width,
+                    idx - width - 1,
+                    idx - width + 1,
+                    idx + width - 1,
+                    idx + width + 1,
+                ]
+                .iter()
+                .filter(|n| self.map.tiles[**n] == TileType::Wall)
+                .count();
+
+                new_tiles[idx] = if neighbours > 4 || neighbours == 0 {
+                    TileType::Wall
+                } else {
+                    TileType::Floor
+                };
+            }
+        }
+
+        self.map.tiles = new_tiles;
+    }
+}
+
+impl MapBuilder for CellularAutomataBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        Position {
+            x: self.starting_position.x,
+            y: self.starting_position.y,
+        }
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        chunk_spawn_regions(&self.map, &self.starting_position)
+    }
+}
@@ -0,0 +1,226 @@
+use super::{Map, Position, Rect, TileType};
+use rltk::{BaseMap, DijkstraMap, DistanceAlg, Point};
+use std::cmp::{max, min};
+
+pub fn apply_room_to_map(map: &mut Map, room: &Rect) {
+    for y in room.y1 + 1..=room.y2 {
+        for x in room.x1 + 1..=room.x2 {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_horizontal_tunnel(map: &mut Map, x1: i32, x2: i32, y: i32) {
+    for x in min(x1, x2)..=max(x1, x2) {
+        if x >= 0 && x < map.width && y >= 0 && y < map.height {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+pub fn apply_vertical_tunnel(map: &mut Map, y1: i32, y2: i32, x: i32) {
+    for y in min(y1, y2)..=max(y1, y2) {
+        if x >= 0 && x < map.width && y >= 0 && y < map.height {
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = TileType::Floor;
+        }
+    }
+}
+
+/// Puts a closed door wherever a corridor passes through the wall of a room.
+pub fn place_doors(map: &mut Map) {
+    let rooms = map.rooms.clone();
+    for room in rooms.iter() {
+        // The walls are just outside of the floor applied by `apply_room_to_map`
+        for x in room.x1 + 1..=room.x2 {
+            place_door_if_corridor(map, x, room.y1);
+            place_door_if_corridor(map, x, room.y2 + 1);
+        }
+        for y in room.y1 + 1..=room.y2 {
+            place_door_if_corridor(map, room.x1, y);
+            place_door_if_corridor(map, room.x2 + 1, y);
+        }
+    }
+}
+
+/// A corridor has walls on both of its sides; anything else is open floor, where a door would
+/// look out of place. Rooms that are very close together share a single door.
+fn place_door_if_corridor(map: &mut Map, x: i32, y: i32) {
+    if x < 1 || x >= map.width - 1 || y < 1 || y >= map.height - 1 {
+        return;
+    }
+    let idx = map.xy_idx(x, y);
+    let tile_is = |x: i32, y: i32, tile: TileType| map.tiles[map.xy_idx(x, y)] == tile;
+
+    let corridor = (tile_is(x - 1, y, TileType::Wall) && tile_is(x + 1, y, TileType::Wall))
+        || (tile_is(x, y - 1, TileType::Wall) && tile_is(x, y + 1, TileType::Wall));
+    let next_to_door = [(x - 1, y), (x + 1, y), (x, y - 1), (x, y + 1)]
+        .iter()
+        .any(|(x, y)| tile_is(*x, *y, TileType::ClosedDoor));
+    if map.tiles[idx] == TileType::Floor && corridor && !next_to_door {
+        map.tiles[idx] = TileType::ClosedDoor;
+    }
+}
+
+/// Spawn regions for room-based maps: the inside of every room but the first, where the player
+/// starts.
+pub fn room_spawn_regions(map: &Map) -> Vec<Vec<usize>> {
+    map.rooms
+        .iter()
+        .skip(1)
+        .map(|room| {
+            let mut region = Vec::new();
+            for y in room.y1 + 1..=room.y2 {
+                for x in room.x1 + 1..=room.x2 {
+                    region.push(map.xy_idx(x, y));
+                }
+            }
+            region
+        })
+        .collect()
+}
+
+/// Spawn regions for maps without rooms: floor tiles are grouped into square chunks of the map.
+/// Tiles close to the player's start are left out, so they don't wake up next to a monster.
+pub fn chunk_spawn_regions(map: &Map, start: &Position) -> Vec<Vec<usize>> {
+    const CHUNK_SIZE: i32 = 10;
+    const SAFE_DISTANCE: f32 = 8.0;
+
+    let chunks_wide = (map.width + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    let chunks_high = (map.height + CHUNK_SIZE - 1) / CHUNK_SIZE;
+    let mut regions: Vec<Vec<usize>> = vec![Vec::new(); (chunks_wide * chunks_high) as usize];
+
+    let start_point = Point::new(start.x, start.y);
+    for y in 1..map.height - 1 {
+        for x in 1..map.width - 1 {
+            let idx = map.xy_idx(x, y);
+            let distance = DistanceAlg::Pythagoras.distance2d(Point::new(x, y), start_point);
+            if map.tiles[idx] == TileType::Floor && distance > SAFE_DISTANCE {
+                let chunk = (y / CHUNK_SIZE) * chunks_wide + (x / CHUNK_SIZE);
+                regions[chunk as usize].push(idx);
+            }
+        }
+    }
+
+    regions.retain(|region| !region.is_empty());
+    regions
+}
+
+/// Finds every group of floor tiles that can reach each other, using the map's own exits.
+fn connected_regions(map: &mut Map) -> Vec<Vec<usize>> {
+    map.populate_blocked();
+
+    let mut visited = vec![false; map.tiles.len()];
+    let mut regions = Vec::new();
+    for idx in 0..map.tiles.len() {
+        if visited[idx] || map.tiles[idx] != TileType::Floor {
+            continue;
+        }
+
+        let mut region = Vec::new();
+        let mut open_list = vec![idx];
+        visited[idx] = true;
+        while let Some(current) = open_list.pop() {
+            region.push(current);
+            for (exit, _cost) in map.get_available_exits(current) {
+                if !visited[exit] {
+                    visited[exit] = true;
+                    open_list.push(exit);
+                }
+            }
+        }
+        regions.push(region);
+    }
+
+    regions
+}
+
+/// Walls off every floor tile that isn't part of the largest connected region of the map.
+pub fn keep_largest_region(map: &mut Map) {
+    let mut regions = connected_regions(map);
+    regions.sort_by_key(|region| std::cmp::Reverse(region.len()));
+    for region in regions.iter().skip(1) {
+        for idx in region.iter() {
+            map.tiles[*idx] = TileType::Wall;
+        }
+    }
+    map.populate_blocked();
+}
+
+/// Returns the floor tile closest to the middle of the map.
+pub fn floor_nearest_center(map: &Map) -> Position {
+    let center = Point::new(map.width / 2, map.height / 2);
+    let idx = (0..map.tiles.len())
+        .filter(|idx| map.tiles[*idx] == TileType::Floor)
+        .min_by(|a, b| {
+            let a = Point::new(*a as i32 % map.width, *a as i32 / map.width);
+            let b = Point::new(*b as i32 % map.width, *b as i32 / map.width);
+            DistanceAlg::PythagorasSquared
+                .distance2d(a, center)
+                .partial_cmp(&DistanceAlg::PythagorasSquared.distance2d(b, center))
+                .unwrap()
+        })
+        .expect("Map has no floor tiles");
+
+    Position {
+        x: idx as i32 % map.width,
+        y: idx as i32 / map.width,
+    }
+}
+
+/// Runs a Dijkstra flood out from `start`, and walls off everything it can't reach.
+/// Returns the distance of every tile from `start`, with `f32::MAX` for unreachable tiles.
+pub fn cull_unreachable(map: &mut Map, start: &Position) -> Vec<f32> {
+    map.populate_blocked();
+    let start_idx = map.xy_idx(start.x, start.y);
+    // No path is longer than a diagonal step onto every tile of the map, so nothing reachable is
+    // ever mistaken for being out of reach, however big the map
+    let max_depth = (map.width * map.height) as f32 * 1.45;
+    let dijkstra = DijkstraMap::new(map.width, map.height, &[start_idx], &*map, max_depth);
+
+    for (idx, distance) in dijkstra.map.iter().enumerate() {
+        if map.tiles[idx] != TileType::Wall && *distance == f32::MAX && idx != start_idx {
+            map.tiles[idx] = TileType::Wall;
+        }
+    }
+    map.populate_blocked();
+
+    dijkstra.map
+}
+
+/// Returns the reachable floor tile with the greatest distance in `distances`.
+pub fn furthest_floor_tile(map: &Map, distances: &[f32]) -> Option<usize> {
+    distances
+        .iter()
+        .enumerate()
+        .filter(|(idx, distance)| map.tiles[*idx] == TileType::Floor && **distance < f32::MAX)
+        .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap())
+        .map(|(idx, _)| idx)
+}
+
+/// Turns the reachable tile furthest away from `start` into the down stairs.
+pub fn place_stairs_furthest_from(map: &mut Map, start: &Position) {
+    let distances = cull_unreachable(map, start);
+    let stairs_idx =
+        furthest_floor_tile(map, &distances).expect("No reachable floor for the stairs");
+    map.tiles[stairs_idx] = TileType::DownStairs;
+}
+
+/// Where the walkers of the drunkard's walk and diffusion-limited aggregation builders start out.
+#[derive(PartialEq, Copy, Clone)]
+pub enum SpawnMode {
+    /// Always from the middle of the map.
+    Centre,
+    /// Anywhere on the map.
+    Random,
+}
+
+/// Counts the floor tiles of a map.
+pub fn floor_tile_count(map: &Map) -> usize {
+    map.tiles
+        .iter()
+        .filter(|tile| **tile == TileType::Floor)
+        .count()
+}
@@ -0,0 +1,158 @@
+use super::{
+    chunk_spawn_regions, floor_tile_count, keep_largest_region, place_stairs_furthest_from, Map,
+    MapBuilder, Position, SpawnMode, TileType,
+};
+use rltk::RandomNumberGenerator;
+
+pub struct DlaSettings {
+    /// `Random` walkers wander in from anywhere until they bump into the existing floor, and
+    /// dig out the tile they came from. `Centre` walkers wander out from the middle of the map
+    /// until they leave the floor, and dig out the wall they stepped into.
+    pub spawn_mode: SpawnMode,
+    /// How many steps a walker takes before it is abandoned.
+    pub walker_lifetime: i32,
+    /// Fraction of the map that should end up as floor, between 0 and 1.
+    pub floor_percent: f32,
+}
+
+/// Diffusion-limited aggregation: floor grows out of a small seed one tile at a time, wherever a
+/// randomly walking particle touches it. This gives branching, eroded looking caves.
+/// See http://www.roguebasin.com/index.php?title=Diffusion-limited_aggregation
+pub struct DlaBuilder {
+    map: Map,
+    starting_position: Position,
+    settings: DlaSettings,
+}
+
+impl DlaBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32, settings: DlaSettings) -> DlaBuilder {
+        DlaBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            settings,
+        }
+    }
+
+    pub fn walk_inwards(new_depth: i32, width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            new_depth,
+            width,
+            height,
+            DlaSettings {
+                spawn_mode: SpawnMode::Random,
+                walker_lifetime: 2000,
+                floor_percent: 0.25,
+            },
+        )
+    }
+
+    pub fn walk_outwards(new_depth: i32, width: i32, height: i32) -> DlaBuilder {
+        DlaBuilder::new(
+            new_depth,
+            width,
+            height,
+            DlaSettings {
+                spawn_mode: SpawnMode::Centre,
+                walker_lifetime: 2000,
+                floor_percent: 0.25,
+            },
+        )
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Give up eventually, in case the map is too cramped to ever reach the target
+        const MAX_WALKERS: i32 = 20000;
+
+        // Start with a small open area for the floor to grow from
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        for y in self.starting_position.y - 1..=self.starting_position.y + 1 {
+            for x in self.starting_position.x - 1..=self.starting_position.x + 1 {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Floor;
+            }
+        }
+
+        let total_tiles = (self.map.width * self.map.height) as usize;
+        let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
+        let mut walker_count = 0;
+
+        while floor_tile_count(&self.map) < desired_floor_tiles && walker_count < MAX_WALKERS {
+            match self.settings.spawn_mode {
+                SpawnMode::Random => self.walk_inwards_once(rng),
+                SpawnMode::Centre => self.walk_outwards_once(rng),
+            }
+            walker_count += 1;
+        }
+
+        keep_largest_region(&mut self.map);
+        place_stairs_furthest_from(&mut self.map, &self.starting_position);
+    }
+
+    fn walk_inwards_once(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut x = rng.roll_dice(1, self.map.width - 3) + 1;
+        let mut y = rng.roll_dice(1, self.map.height - 3) + 1;
+        if self.map.tiles[self.map.xy_idx(x, y)] == TileType::Floor {
+            return;
+        }
+
+        for _step in 0..self.settings.walker_lifetime {
+            let (prev_x, prev_y) = (x, y);
+            self.stagger(&mut x, &mut y, rng);
+            if self.map.tiles[self.map.xy_idx(x, y)] == TileType::Floor {
+                let idx = self.map.xy_idx(prev_x, prev_y);
+                self.map.tiles[idx] = TileType::Floor;
+                return;
+            }
+        }
+    }
+
+    fn walk_outwards_once(&mut self, rng: &mut RandomNumberGenerator) {
+        let mut x = self.starting_position.x;
+        let mut y = self.starting_position.y;
+
+        for _step in 0..self.settings.walker_lifetime {
+            self.stagger(&mut x, &mut y, rng);
+            let idx = self.map.xy_idx(x, y);
+            if self.map.tiles[idx] == TileType::Wall {
+                self.map.tiles[idx] = TileType::Floor;
+                return;
+            }
+        }
+    }
+
+    /// Takes a random step, without ever reaching the outer edge of the map.
+    fn stagger(&self, x: &mut i32, y: &mut i32, rng: &mut RandomNumberGenerator) {
+        match rng.roll_dice(1, 4) {
+            1 => *x -= 1,
+            2 => *x += 1,
+            3 => *y -= 1,
+            _ => *y += 1,
+        }
+        *x = (*x).clamp(1, self.map.width - 2);
+        *y = (*y).clamp(1, self.map.height - 2);
+    }
+}
+
+impl MapBuilder for DlaBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        Position {
+            x: self.starting_position.x,
+            y: self.starting_position.y,
+        }
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        chunk_spawn_regions(&self.map, &self.starting_position)
+    }
+}
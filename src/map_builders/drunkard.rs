@@ -0,0 +1,160 @@
+use super::{
+    chunk_spawn_regions, floor_nearest_center, floor_tile_count, keep_largest_region,
+    place_stairs_furthest_from, Map, MapBuilder, Position, SpawnMode, TileType,
+};
+use rltk::RandomNumberGenerator;
+
+pub struct DrunkardSettings {
+    pub spawn_mode: SpawnMode,
+    /// How many steps each drunkard takes before passing out.
+    pub drunken_lifetime: i32,
+    /// Fraction of the map that should end up as floor, between 0 and 1.
+    pub floor_percent: f32,
+}
+
+/// Drunkard's walk: stumbling diggers carve out floor at random until enough of the map is open.
+/// See http://www.roguebasin.com/index.php?title=Random_Walk_Cave_Generation
+pub struct DrunkardsWalkBuilder {
+    map: Map,
+    starting_position: Position,
+    settings: DrunkardSettings,
+}
+
+impl DrunkardsWalkBuilder {
+    pub fn new(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        settings: DrunkardSettings,
+    ) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            settings,
+        }
+    }
+
+    /// Wide open caverns, all dug out from the middle of the map.
+    pub fn open_area(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: SpawnMode::Centre,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+            },
+        )
+    }
+
+    /// Long halls branching off each other.
+    pub fn open_halls(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: SpawnMode::Random,
+                drunken_lifetime: 400,
+                floor_percent: 0.5,
+            },
+        )
+    }
+
+    /// Narrow twisting passages, from many short-lived diggers.
+    pub fn winding_passages(new_depth: i32, width: i32, height: i32) -> DrunkardsWalkBuilder {
+        DrunkardsWalkBuilder::new(
+            new_depth,
+            width,
+            height,
+            DrunkardSettings {
+                spawn_mode: SpawnMode::Random,
+                drunken_lifetime: 100,
+                floor_percent: 0.4,
+            },
+        )
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        // Give up eventually, in case the map is too cramped to ever reach the target
+        const MAX_DIGGERS: i32 = 1000;
+
+        self.starting_position = Position {
+            x: self.map.width / 2,
+            y: self.map.height / 2,
+        };
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        self.map.tiles[start_idx] = TileType::Floor;
+
+        let total_tiles = (self.map.width * self.map.height) as usize;
+        let desired_floor_tiles = (self.settings.floor_percent * total_tiles as f32) as usize;
+        let mut digger_count = 0;
+
+        while floor_tile_count(&self.map) < desired_floor_tiles && digger_count < MAX_DIGGERS {
+            let (mut x, mut y) = match self.settings.spawn_mode {
+                SpawnMode::Centre => (self.starting_position.x, self.starting_position.y),
+                SpawnMode::Random => {
+                    if digger_count == 0 {
+                        (self.starting_position.x, self.starting_position.y)
+                    } else {
+                        (
+                            rng.roll_dice(1, self.map.width - 3) + 1,
+                            rng.roll_dice(1, self.map.height - 3) + 1,
+                        )
+                    }
+                }
+            };
+
+            for _step in 0..self.settings.drunken_lifetime {
+                let idx = self.map.xy_idx(x, y);
+                self.map.tiles[idx] = TileType::Floor;
+
+                match rng.roll_dice(1, 4) {
+                    1 => x -= 1,
+                    2 => x += 1,
+                    3 => y -= 1,
+                    _ => y += 1,
+                }
+                // Keep the outer edge of the map solid
+                x = x.clamp(1, self.map.width - 2);
+                y = y.clamp(1, self.map.height - 2);
+            }
+
+            digger_count += 1;
+        }
+
+        // Diggers that started out in the middle of nowhere can leave disconnected pockets
+        keep_largest_region(&mut self.map);
+        let start_idx = self
+            .map
+            .xy_idx(self.starting_position.x, self.starting_position.y);
+        if self.map.tiles[start_idx] != TileType::Floor {
+            self.starting_position = floor_nearest_center(&self.map);
+        }
+        place_stairs_furthest_from(&mut self.map, &self.starting_position);
+    }
+}
+
+impl MapBuilder for DrunkardsWalkBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        Position {
+            x: self.starting_position.x,
+            y: self.starting_position.y,
+        }
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        chunk_spawn_regions(&self.map, &self.starting_position)
+    }
+}
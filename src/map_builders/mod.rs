@@ -0,0 +1,278 @@
+use super::{raws::RawMaster, Map, Position, Rect, TileType};
+use rltk::RandomNumberGenerator;
+
+mod common;
+use common::*;
+
+mod simple_map;
+use simple_map::SimpleMapBuilder;
+
+mod bsp_dungeon;
+use bsp_dungeon::BspDungeonBuilder;
+
+mod cellular_automata;
+use cellular_automata::CellularAutomataBuilder;
+
+mod drunkard;
+use drunkard::DrunkardsWalkBuilder;
+
+mod dla;
+use dla::DlaBuilder;
+
+mod prefab_builder;
+pub use prefab_builder::check_prefabs;
+use prefab_builder::PrefabBuilder;
+
+/// A map generation algorithm. Builders own the map while it is being made, and hand out a
+/// copy of it once `build_map` has run.
+pub trait MapBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator);
+    fn get_map(&self) -> Map;
+    fn get_starting_position(&self) -> Position;
+    /// Groups of tile indices that monsters may be spawned in. Maps without rooms (such as caves)
+    /// divide their floor up however suits them.
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>>;
+    /// Specific entities to place on specific tiles, such as the inhabitants of a prefab. They are
+    /// given by the glyph they are drawn with, and looked up in the raws.
+    fn get_spawn_glyphs(&self) -> Vec<(usize, char)> {
+        Vec::new()
+    }
+}
+
+/// Makes a builder for the given depth, width and height.
+type BuilderConstructor = fn(i32, i32, i32) -> Box<dyn MapBuilder>;
+
+/// Every available builder, by the name used to select it (e.g. with `--builder`).
+const BUILDERS: &[(&str, BuilderConstructor)] = &[
+    ("rooms", |depth, width, height| {
+        Box::new(SimpleMapBuilder::new(depth, width, height))
+    }),
+    ("bsp", |depth, width, height| {
+        Box::new(BspDungeonBuilder::new(depth, width, height))
+    }),
+    ("caves", |depth, width, height| {
+        Box::new(CellularAutomataBuilder::new(depth, width, height))
+    }),
+    ("drunkard-open", |depth, width, height| {
+        Box::new(DrunkardsWalkBuilder::open_area(depth, width, height))
+    }),
+    ("drunkard-halls", |depth, width, height| {
+        Box::new(DrunkardsWalkBuilder::open_halls(depth, width, height))
+    }),
+    ("drunkard-winding", |depth, width, height| {
+        Box::new(DrunkardsWalkBuilder::winding_passages(depth, width, height))
+    }),
+    ("dla-inwards", |depth, width, height| {
+        Box::new(DlaBuilder::walk_inwards(depth, width, height))
+    }),
+    ("dla-outwards", |depth, width, height| {
+        Box::new(DlaBuilder::walk_outwards(depth, width, height))
+    }),
+    ("fortress", |depth, width, height| {
+        Box::new(PrefabBuilder::fortress(depth, width, height))
+    }),
+    ("rooms-vaults", |depth, width, height| {
+        Box::new(PrefabBuilder::vaults(
+            depth,
+            width,
+            height,
+            Box::new(SimpleMapBuilder::new(depth, width, height)),
+        ))
+    }),
+    ("drunkard-vaults", |depth, width, height| {
+        Box::new(PrefabBuilder::vaults(
+            depth,
+            width,
+            height,
+            Box::new(DrunkardsWalkBuilder::open_area(depth, width, height)),
+        ))
+    }),
+];
+
+/// The smallest and largest maps levels can be built on. Some builders need more room than the
+/// smallest, see `min_map_size`. Past the largest, some builders take too long or can't fill
+/// enough of the map.
+const MIN_MAP_SIZE: (i32, i32) = (20, 20);
+const MAX_MAP_SIZE: (i32, i32) = (200, 120);
+
+/// The smallest map a builder can make a level on. A handcrafted level needs room for all of it.
+fn min_map_size(name: &str) -> (i32, i32) {
+    match name {
+        "fortress" => {
+            let (width, height) = PrefabBuilder::fortress_size();
+            (width.max(MIN_MAP_SIZE.0), height.max(MIN_MAP_SIZE.1))
+        }
+        _ => MIN_MAP_SIZE,
+    }
+}
+
+fn fits(name: &str, width: i32, height: i32) -> bool {
+    let (min_width, min_height) = min_map_size(name);
+    width >= min_width && height >= min_height
+}
+
+/// Checks that levels can be built on maps of the given size, by the named builder or, with
+/// `None`, by the randomly picked ones. Returns what is wrong if they can't.
+pub fn check_map_size(builder_name: Option<&str>, width: i32, height: i32) -> Result<(), String> {
+    let (max_width, max_height) = MAX_MAP_SIZE;
+    if width > max_width || height > max_height {
+        return Err(format!(
+            "Maps can be at most {}x{}, not {}x{}",
+            max_width, max_height, width, height
+        ));
+    }
+    let name = builder_name.unwrap_or("");
+    if !fits(name, width, height) {
+        let (min_width, min_height) = min_map_size(name);
+        return Err(match builder_name {
+            Some(name) => format!(
+                "The '{}' builder needs a map of at least {}x{}, not {}x{}",
+                name, min_width, min_height, width, height
+            ),
+            None => format!(
+                "Maps must be at least {}x{}, not {}x{}",
+                min_width, min_height, width, height
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Names of all builders that can be passed to `builder_by_name`.
+pub fn builder_names() -> Vec<&'static str> {
+    BUILDERS.iter().map(|(name, _)| *name).collect()
+}
+
+fn builder_by_name(
+    name: &str,
+    new_depth: i32,
+    width: i32,
+    height: i32,
+) -> Option<Box<dyn MapBuilder>> {
+    BUILDERS
+        .iter()
+        .find(|(builder_name, _)| *builder_name == name)
+        .map(|(_, constructor)| constructor(new_depth, width, height))
+}
+
+/// How levels get made: which builder to use, and how big the maps are.
+pub struct MapGenSettings {
+    /// `None` picks a random builder for every level.
+    pub builder_name: Option<String>,
+    pub width: i32,
+    pub height: i32,
+}
+
+/// A finished, validated level: every floor tile can be reached from the starting position.
+pub struct Level {
+    pub map: Map,
+    pub starting_position: Position,
+    pub spawn_regions: Vec<Vec<usize>>,
+    pub spawn_list: Vec<(usize, String)>,
+    /// How far every tile is from the starting position, or `f32::MAX` if it can't be reached.
+    /// Useful for placing things as far away from the player as possible.
+    pub distance_map: Vec<f32>,
+}
+
+impl Level {
+    /// The floor tile furthest from the starting position, to put the exit on.
+    pub fn furthest_floor_tile(&self) -> Option<usize> {
+        furthest_floor_tile(&self.map, &self.distance_map)
+    }
+}
+
+/// Levels with fewer floor tiles than this fraction of the map are thrown away and rebuilt.
+const MIN_FLOOR_FRACTION: f32 = 0.1;
+const MAX_BUILD_ATTEMPTS: i32 = 100;
+
+/// Builds a level with the named builder (or a random one for each attempt), then cleans it up:
+/// anything the player can't walk to from the start is walled off, and the stairs are moved to
+/// the furthest point if they ended up unreachable. Levels left too small are regenerated.
+pub fn generate_level(
+    settings: &MapGenSettings,
+    new_depth: i32,
+    raws: &RawMaster,
+    rng: &mut RandomNumberGenerator,
+) -> Level {
+    let (width, height) = (settings.width, settings.height);
+    for _attempt in 0..MAX_BUILD_ATTEMPTS {
+        let mut builder = match &settings.builder_name {
+            Some(name) => {
+                builder_by_name(name, new_depth, width, height).expect("Unknown map builder")
+            }
+            None => random_builder(new_depth, width, height, rng),
+        };
+        builder.build_map(rng);
+
+        let mut map = builder.get_map();
+        let starting_position = builder.get_starting_position();
+        let distance_map = cull_unreachable(&mut map, &starting_position);
+
+        let min_floor_tiles = (map.tiles.len() as f32 * MIN_FLOOR_FRACTION) as usize;
+        if floor_tile_count(&map) < min_floor_tiles {
+            continue;
+        }
+
+        // Nothing may be spawned on tiles that were walled off
+        let spawn_regions = builder
+            .get_spawn_regions()
+            .into_iter()
+            .map(|region| {
+                region
+                    .into_iter()
+                    .filter(|idx| map.tiles[*idx] == TileType::Floor)
+                    .collect::<Vec<usize>>()
+            })
+            .filter(|region| !region.is_empty())
+            .collect();
+        let spawn_list = builder
+            .get_spawn_glyphs()
+            .into_iter()
+            .filter(|(idx, _)| map.tiles[*idx] != TileType::Wall)
+            .map(|(idx, glyph)| {
+                let name = raws
+                    .name_for_glyph(glyph)
+                    .expect("Prefabs are checked against the raws when the game starts");
+                (idx, name.to_string())
+            })
+            .collect();
+
+        let mut level = Level {
+            map,
+            starting_position,
+            spawn_regions,
+            spawn_list,
+            distance_map,
+        };
+        if !level.map.tiles.contains(&TileType::DownStairs) {
+            let stairs_idx = level
+                .furthest_floor_tile()
+                .expect("No reachable floor for the stairs");
+            level.map.tiles[stairs_idx] = TileType::DownStairs;
+        }
+
+        return level;
+    }
+
+    panic!(
+        "Unable to build a usable level after {} attempts",
+        MAX_BUILD_ATTEMPTS
+    );
+}
+
+/// Picks any builder that fits the map at random. Vaults come from the `*-vaults` builders, so
+/// nothing gets them stamped in twice, and the fortress is left as it was drawn.
+fn random_builder(
+    new_depth: i32,
+    width: i32,
+    height: i32,
+    rng: &mut RandomNumberGenerator,
+) -> Box<dyn MapBuilder> {
+    let builders: Vec<&(&str, BuilderConstructor)> = BUILDERS
+        .iter()
+        .filter(|(name, _)| fits(name, width, height))
+        .collect();
+    let roll = rng.roll_dice(1, builders.len() as i32) - 1;
+    let (_, constructor) = builders[roll as usize];
+    constructor(new_depth, width, height)
+}
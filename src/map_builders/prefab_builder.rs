@@ -0,0 +1,308 @@
+use super::{place_stairs_furthest_from, Map, MapBuilder, Position, RawMaster, TileType};
+use crate::resources::read_resource;
+use rltk::RandomNumberGenerator;
+use std::collections::HashSet;
+
+/// Handcrafted levels and vaults, drawn as ASCII art in `resources/prefabs`:
+///
+/// * `#` wall, `.` floor, `>` down stairs
+/// * `@` where the player starts (whole levels only)
+/// * the glyph of any monster or item in the raws (such as `g` for a goblin), standing on floor
+/// * a space leaves whatever the map already had there
+///
+/// They are read each time they are used, falling back to the copy built into the game.
+type PrefabFile = (&'static str, &'static str);
+
+const FORTRESS: PrefabFile = (
+    "resources/prefabs/fortress.txt",
+    include_str!("../../resources/prefabs/fortress.txt"),
+);
+
+/// Small set pieces that get stamped into open floor. They are bordered by floor, so they never
+/// cut off the area around them.
+const VAULTS: &[PrefabFile] = &[
+    (
+        "resources/prefabs/goblin_camp.txt",
+        include_str!("../../resources/prefabs/goblin_camp.txt"),
+    ),
+    (
+        "resources/prefabs/orc_den.txt",
+        include_str!("../../resources/prefabs/orc_den.txt"),
+    ),
+    (
+        "resources/prefabs/pillared_hall.txt",
+        include_str!("../../resources/prefabs/pillared_hall.txt"),
+    ),
+    (
+        "resources/prefabs/ambush.txt",
+        include_str!("../../resources/prefabs/ambush.txt"),
+    ),
+];
+
+const MAX_VAULTS: i32 = 3;
+
+/// The cells that are map tiles, rather than something to spawn.
+const TILE_CELLS: &[char] = &[' ', '#', '.', '@', '>'];
+
+/// Checks that every other cell in the prefabs is drawn with the glyph of a monster or item in
+/// the raws, so that a mistake is reported when the game starts rather than partway through it.
+pub fn check_prefabs(raws: &RawMaster) {
+    for file in VAULTS.iter().chain(std::iter::once(&FORTRESS)) {
+        let prefab = Prefab::load(*file);
+        for y in 0..prefab.height {
+            for x in 0..prefab.width {
+                let cell = prefab.cell(x, y);
+                if !TILE_CELLS.contains(&cell) && raws.name_for_glyph(cell).is_none() {
+                    let row: String = (0..prefab.width).map(|x| prefab.cell(x, y)).collect();
+                    panic!(
+                        "Unknown glyph '{}' in {}, on the line \"{}\"",
+                        cell, file.0, row
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// A parsed prefab, stored row by row.
+struct Prefab {
+    width: i32,
+    height: i32,
+    cells: Vec<char>,
+}
+
+impl Prefab {
+    fn load((path, embedded): PrefabFile) -> Prefab {
+        Prefab::parse(&read_resource(path, embedded))
+    }
+
+    fn parse(template: &str) -> Prefab {
+        let lines: Vec<&str> = template
+            .lines()
+            .map(|line| line.trim_end_matches('\r'))
+            .filter(|line| !line.is_empty())
+            .collect();
+        let width = lines
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+
+        // Short lines are padded out with spaces, leaving the map alone
+        let mut cells = Vec::with_capacity(width * lines.len());
+        for line in lines.iter() {
+            let mut row: Vec<char> = line.chars().collect();
+            row.resize(width, ' ');
+            cells.extend(row);
+        }
+
+        Prefab {
+            width: width as i32,
+            height: lines.len() as i32,
+            cells,
+        }
+    }
+
+    fn cell(&self, x: i32, y: i32) -> char {
+        self.cells[(y * self.width + x) as usize]
+    }
+}
+
+pub enum PrefabMode {
+    /// The whole level comes from a single prefab, centred on the map.
+    Level { file: PrefabFile },
+    /// Another builder makes the level, and vaults are stamped into its open areas.
+    Vaults { previous: Box<dyn MapBuilder> },
+}
+
+pub struct PrefabBuilder {
+    map: Map,
+    starting_position: Position,
+    mode: PrefabMode,
+    spawn_glyphs: Vec<(usize, char)>,
+    vault_tiles: HashSet<usize>,
+}
+
+impl PrefabBuilder {
+    pub fn new(new_depth: i32, width: i32, height: i32, mode: PrefabMode) -> PrefabBuilder {
+        PrefabBuilder {
+            map: Map::new(new_depth, width, height),
+            starting_position: Position { x: 0, y: 0 },
+            mode,
+            spawn_glyphs: Vec::new(),
+            vault_tiles: HashSet::new(),
+        }
+    }
+
+    pub fn fortress(new_depth: i32, width: i32, height: i32) -> PrefabBuilder {
+        PrefabBuilder::new(
+            new_depth,
+            width,
+            height,
+            PrefabMode::Level { file: FORTRESS },
+        )
+    }
+
+    /// The size of the fortress prefab: the smallest map it fits on.
+    pub fn fortress_size() -> (i32, i32) {
+        let prefab = Prefab::load(FORTRESS);
+        (prefab.width, prefab.height)
+    }
+
+    pub fn vaults(
+        new_depth: i32,
+        width: i32,
+        height: i32,
+        previous: Box<dyn MapBuilder>,
+    ) -> PrefabBuilder {
+        PrefabBuilder::new(new_depth, width, height, PrefabMode::Vaults { previous })
+    }
+
+    fn build(&mut self, rng: &mut RandomNumberGenerator) {
+        match &mut self.mode {
+            PrefabMode::Level { file } => {
+                let prefab = Prefab::load(*file);
+                self.load_level(&prefab);
+            }
+            PrefabMode::Vaults { previous } => {
+                previous.build_map(rng);
+                self.map = previous.get_map();
+                self.starting_position = previous.get_starting_position();
+                self.spawn_glyphs = previous.get_spawn_glyphs();
+                self.apply_vaults(rng);
+            }
+        }
+    }
+
+    fn load_level(&mut self, prefab: &Prefab) {
+        if prefab.width > self.map.width || prefab.height > self.map.height {
+            // Leave the map solid; it will be rejected as unusable
+            return;
+        }
+        let offset_x = (self.map.width - prefab.width) / 2;
+        let offset_y = (self.map.height - prefab.height) / 2;
+
+        let mut found_start = false;
+        for y in 0..prefab.height {
+            for x in 0..prefab.width {
+                let (map_x, map_y) = (x + offset_x, y + offset_y);
+                if prefab.cell(x, y) == '@' {
+                    self.starting_position = Position { x: map_x, y: map_y };
+                    found_start = true;
+                }
+                self.stamp(prefab.cell(x, y), map_x, map_y);
+            }
+        }
+        assert!(found_start, "Prefab level has no starting position (@)");
+
+        if !self.map.tiles.contains(&TileType::DownStairs) {
+            place_stairs_furthest_from(&mut self.map, &self.starting_position);
+        }
+    }
+
+    /// Stamps a few random vaults wherever they fit entirely on open floor, without covering the
+    /// player's start, the stairs, another vault or anything already due to spawn.
+    fn apply_vaults(&mut self, rng: &mut RandomNumberGenerator) {
+        let vault_count = rng.roll_dice(1, MAX_VAULTS);
+        let mut used_tiles: HashSet<usize> =
+            self.spawn_glyphs.iter().map(|(idx, _)| *idx).collect();
+        used_tiles.insert(
+            self.map
+                .xy_idx(self.starting_position.x, self.starting_position.y),
+        );
+
+        for _i in 0..vault_count {
+            let prefab = Prefab::load(*rng.random_slice_entry(VAULTS).unwrap());
+
+            let mut candidates = Vec::new();
+            for y in 1..self.map.height - prefab.height {
+                for x in 1..self.map.width - prefab.width {
+                    if self.vault_fits(&prefab, x, y, &used_tiles) {
+                        candidates.push((x, y));
+                    }
+                }
+            }
+
+            if let Some((x, y)) = rng.random_slice_entry(&candidates).copied() {
+                for vy in 0..prefab.height {
+                    for vx in 0..prefab.width {
+                        let idx = self.map.xy_idx(x + vx, y + vy);
+                        used_tiles.insert(idx);
+                        self.vault_tiles.insert(idx);
+                        self.stamp(prefab.cell(vx, vy), x + vx, y + vy);
+                    }
+                }
+            }
+        }
+    }
+
+    fn vault_fits(&self, prefab: &Prefab, x: i32, y: i32, used_tiles: &HashSet<usize>) -> bool {
+        for vy in 0..prefab.height {
+            for vx in 0..prefab.width {
+                let idx = self.map.xy_idx(x + vx, y + vy);
+                if self.map.tiles[idx] != TileType::Floor || used_tiles.contains(&idx) {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    fn stamp(&mut self, cell: char, x: i32, y: i32) {
+        let idx = self.map.xy_idx(x, y);
+        match cell {
+            ' ' => {}
+            '#' => self.map.tiles[idx] = TileType::Wall,
+            '.' | '@' => self.map.tiles[idx] = TileType::Floor,
+            '>' => self.map.tiles[idx] = TileType::DownStairs,
+            glyph => {
+                self.map.tiles[idx] = TileType::Floor;
+                self.spawn_glyphs.push((idx, glyph));
+            }
+        }
+    }
+}
+
+impl MapBuilder for PrefabBuilder {
+    fn build_map(&mut self, rng: &mut RandomNumberGenerator) {
+        self.build(rng);
+    }
+
+    fn get_map(&self) -> Map {
+        self.map.clone()
+    }
+
+    fn get_starting_position(&self) -> Position {
+        Position {
+            x: self.starting_position.x,
+            y: self.starting_position.y,
+        }
+    }
+
+    fn get_spawn_regions(&self) -> Vec<Vec<usize>> {
+        match &self.mode {
+            // Prefab levels place their own monsters
+            PrefabMode::Level { .. } => Vec::new(),
+            PrefabMode::Vaults { previous } => {
+                // Vaults bring their own inhabitants, so keep random spawns out of them
+                previous
+                    .get_spawn_regions()
+                    .into_iter()
+                    .map(|region| {
+                        region
+                            .into_iter()
+                            .filter(|idx| {
+                                self.map.tiles[*idx] == TileType::Floor
+                                    && !self.vault_tiles.contains(idx)
+                            })
+                            .collect()
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    fn get_spawn_glyphs(&self) -> Vec<(usize, char)> {
+        self.spawn_glyphs.clone()
+    }
+}
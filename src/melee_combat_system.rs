@@ -1,4 +1,8 @@
-use super::{gamelog::GameLog, CombatStats, Name, SufferDamage, WantsToMelee};
+use super::{
+    gamelog::{GameLog, LogCategory, LogEntry},
+    CombatStats, Name, Renderable, SufferDamage, WantsToMelee,
+};
+use rltk::RandomNumberGenerator;
 use specs::prelude::*;
 
 pub struct MeleeCombatSystem {}
@@ -8,36 +12,57 @@ impl<'a> System<'a> for MeleeCombatSystem {
     type SystemData = (
         Entities<'a>,
         WriteExpect<'a, GameLog>,
+        WriteExpect<'a, RandomNumberGenerator>,
         WriteStorage<'a, WantsToMelee>,
         ReadStorage<'a, Name>,
         ReadStorage<'a, CombatStats>,
         WriteStorage<'a, SufferDamage>,
+        ReadStorage<'a, Renderable>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut log, mut wants_melee, names, combat_stats, mut inflict_damage) = data;
+        let (
+            entities,
+            mut log,
+            mut rng,
+            mut wants_melee,
+            names,
+            combat_stats,
+            mut inflict_damage,
+            renderables,
+        ) = data;
 
-        for (_entity, wants_melee, name, stats) in
+        for (entity, wants_melee, name, stats) in
             (&entities, &wants_melee, &names, &combat_stats).join()
         {
             if stats.curr_hp > 0 {
                 let target_stats = combat_stats.get(wants_melee.target).unwrap();
                 if target_stats.curr_hp > 0 {
-                    let target_name = names.get(wants_melee.target).unwrap();
-
-                    let damage = i32::max(0, stats.power - target_stats.defense);
+                    // Blows land with a little variance: -1, 0 or +1 to the attacker's power
+                    let variance = rng.roll_dice(1, 3) - 2;
+                    let damage = i32::max(0, stats.power + variance - target_stats.defense);
 
+                    let entry =
+                        LogEntry::new(LogCategory::Combat).name_of(entity, &names, &renderables);
                     if damage == 0 {
-                        log.entries.push(format!(
-                            "{} is unable to hurt {}",
-                            &name.name, &target_name.name
+                        log.add(entry.append(" is unable to hurt ").name_of(
+                            wants_melee.target,
+                            &names,
+                            &renderables,
                         ));
                     } else {
-                        log.entries.push(format!(
-                            "{} hits {}, for {} hp.",
-                            &name.name, &target_name.name, damage
-                        ));
-                        SufferDamage::new_damage(&mut inflict_damage, wants_melee.target, damage);
+                        log.add(
+                            entry
+                                .append(" hits ")
+                                .name_of(wants_melee.target, &names, &renderables)
+                                .damage(damage),
+                        );
+                        SufferDamage::new_damage(
+                            &mut inflict_damage,
+                            wants_melee.target,
+                            damage,
+                            &name.name,
+                        );
                     }
                 }
             }
@@ -1,4 +1,7 @@
-use super::{Map, Monster, Position, RunState, Viewshed, WantsToMelee};
+use super::{
+    initiative_system::{spend_turn, ACTION_COST},
+    CombatStats, Energy, Map, Monster, MyTurn, Position, TileType, Viewshed, WantsToMelee,
+};
 use rltk::Point;
 use specs::prelude::*;
 
@@ -10,12 +13,14 @@ impl<'a> System<'a> for MonsterAI {
         WriteExpect<'a, Map>,
         ReadExpect<'a, Point>,
         ReadExpect<'a, Entity>,
-        ReadExpect<'a, RunState>,
         Entities<'a>,
         WriteStorage<'a, Viewshed>,
         ReadStorage<'a, Monster>,
         WriteStorage<'a, Position>,
         WriteStorage<'a, WantsToMelee>,
+        WriteStorage<'a, Energy>,
+        WriteStorage<'a, MyTurn>,
+        ReadStorage<'a, CombatStats>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
@@ -23,21 +28,39 @@ impl<'a> System<'a> for MonsterAI {
             mut map,
             player_pos,
             player_entity,
-            runstate,
             entities,
             mut viewshed,
             monster,
             mut position,
             mut wants_to_melee,
+            mut energies,
+            mut turns,
+            combat_stats,
         ) = data;
 
-        if *runstate != RunState::MonsterTurn {
-            return;
-        }
+        let mut door_opened = false;
+        let mut acted = Vec::new();
 
-        for (entity, mut viewshed, _monster, mut pos) in
-            (&entities, &mut viewshed, &monster, &mut position).join()
+        for (entity, viewshed, _monster, pos, energy, _turn) in (
+            &entities,
+            &mut viewshed,
+            &monster,
+            &mut position,
+            &energies,
+            &turns,
+        )
+            .join()
         {
+            // The dead are only removed once the systems have run
+            if combat_stats
+                .get(entity)
+                .is_some_and(|stats| stats.curr_hp < 1)
+            {
+                continue;
+            }
+
+            // Waiting around takes as long as anything else
+            let mut cost = ACTION_COST;
             let distance =
                 rltk::DistanceAlg::Pythagoras.distance2d(Point::new(pos.x, pos.y), *player_pos);
             if distance < 1.5 {
@@ -49,14 +72,22 @@ impl<'a> System<'a> for MonsterAI {
                         },
                     )
                     .expect("Unable to insert attack");
+                cost = energy.attack_cost;
             } else if viewshed.visible_tiles.contains(&*player_pos) {
                 // Path to the player
                 let path = rltk::a_star_search(
                     map.xy_idx(pos.x, pos.y),
                     map.xy_idx(player_pos.x, player_pos.y),
-                    &mut *map,
+                    &*map,
                 );
-                if path.success && path.steps.len() > 1 {
+                if path.success
+                    && path.steps.len() > 1
+                    && map.tiles[path.steps[1]] == TileType::ClosedDoor
+                {
+                    // Opening the door takes up the move
+                    map.open_door(path.steps[1]);
+                    door_opened = true;
+                } else if path.success && path.steps.len() > 1 {
                     let mut idx = map.xy_idx(pos.x, pos.y);
                     map.blocked[idx] = false;
                     pos.x = path.steps[1] as i32 % map.width;
@@ -66,6 +97,18 @@ impl<'a> System<'a> for MonsterAI {
                     viewshed.dirty = true;
                 }
             }
+            acted.push((entity, cost));
+        }
+
+        for (entity, cost) in acted {
+            spend_turn(&mut energies, &mut turns, entity, cost);
+        }
+
+        if door_opened {
+            // Anyone might be able to see through the doorway now
+            for viewshed in (&mut viewshed).join() {
+                viewshed.dirty = true;
+            }
         }
     }
 }
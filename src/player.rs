@@ -1,16 +1,26 @@
-use super::{CombatStats, Map, Player, Position, RunState, State, Viewshed, WantsToMelee};
-use rltk::{Point, Rltk, VirtualKeyCode};
+use super::{
+    gamelog::{GameLog, LogCategory},
+    initiative_system::{spend_turn, ACTION_COST},
+    BlocksTile, CombatStats, Energy, InBackpack, IncreasesMaxHp, InflictsDamage, Item, KnownSpells,
+    Map, MyTurn, Name, Player, Position, ProvidesHealing, Ranged, RestoresMana, RunState, TileType,
+    Viewshed, WantsToCastSpell, WantsToDropItem, WantsToMelee, WantsToPickupItem, WantsToUseItem,
+};
+use rltk::{DistanceAlg, Point, Rltk, VirtualKeyCode};
 use specs::prelude::*;
-use std::cmp::{max, min};
 
-pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
+/// Moves the player, or attacks or opens whatever is in the way. Returns the energy that took,
+/// or `None` if nothing happened.
+pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) -> Option<i32> {
     let mut positions = ecs.write_storage::<Position>();
     let players = ecs.read_storage::<Player>();
     let mut viewsheds = ecs.write_storage::<Viewshed>();
     let entities = ecs.entities();
     let combat_stats = ecs.read_storage::<CombatStats>();
-    let map = ecs.fetch::<Map>();
+    let mut map = ecs.fetch_mut::<Map>();
     let mut wants_to_melee = ecs.write_storage::<WantsToMelee>();
+    let energies = ecs.read_storage::<Energy>();
+    let mut door_opened = false;
+    let mut cost = None;
 
     for (entity, _player, pos, viewshed) in
         (&entities, &players, &mut positions, &mut viewsheds).join()
@@ -20,7 +30,7 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
             || pos.y + delta_y < 1
             || pos.y + delta_y > map.height - 1
         {
-            return;
+            return None;
         }
         let destination_idx = map.xy_idx(pos.x + delta_x, pos.y + delta_y);
 
@@ -35,23 +45,378 @@ pub fn try_move_player(delta_x: i32, delta_y: i32, ecs: &mut World) {
                         },
                     )
                     .expect("Add target failed");
-                return;
+                return Some(energies.get(entity).map_or(ACTION_COST, |e| e.attack_cost));
             }
         }
 
-        if !map.blocked[destination_idx] {
-            pos.x = min(79, max(0, pos.x + delta_x));
-            pos.y = min(49, max(0, pos.y + delta_y));
+        if map.tiles[destination_idx] == TileType::ClosedDoor {
+            // Walking into a door opens it, and takes up the move
+            map.open_door(destination_idx);
+            door_opened = true;
+            cost = Some(ACTION_COST);
+        } else if !map.blocked[destination_idx] {
+            pos.x += delta_x;
+            pos.y += delta_y;
 
             viewshed.dirty = true;
             let mut ppos = ecs.write_resource::<Point>();
             ppos.x = pos.x;
             ppos.y = pos.y;
+            cost = Some(ACTION_COST);
+        }
+    }
+
+    if door_opened {
+        // Anyone might be able to see through the doorway now
+        for viewshed in (&mut viewsheds).join() {
+            viewshed.dirty = true;
+        }
+    }
+    cost
+}
+
+/// Closes every open door next to the player that nobody is standing in. Items lying in the
+/// doorway don't stop it. Returns false if there was no door to close.
+pub fn try_close_doors(ecs: &mut World) -> bool {
+    let player_pos = *ecs.fetch::<Point>();
+    let mut map = ecs.fetch_mut::<Map>();
+    let blockers = ecs.read_storage::<BlocksTile>();
+    let combat_stats = ecs.read_storage::<CombatStats>();
+    let mut closed_any = false;
+
+    for y in player_pos.y - 1..=player_pos.y + 1 {
+        for x in player_pos.x - 1..=player_pos.x + 1 {
+            if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                continue;
+            }
+            let idx = map.xy_idx(x, y);
+            let occupied = map.tile_content[idx]
+                .iter()
+                .any(|entity| blockers.contains(*entity) || combat_stats.contains(*entity));
+            if map.tiles[idx] == TileType::OpenDoor && !occupied {
+                map.close_door(idx);
+                closed_any = true;
+            }
+        }
+    }
+
+    let mut gamelog = ecs.fetch_mut::<GameLog>();
+    if closed_any {
+        let mut viewsheds = ecs.write_storage::<Viewshed>();
+        for viewshed in (&mut viewsheds).join() {
+            viewshed.dirty = true;
+        }
+        gamelog.log(LogCategory::System, "You close the door.");
+    } else {
+        gamelog.log(LogCategory::System, "There is no open door next to you.");
+    }
+    closed_any
+}
+
+/// Checks that the player is standing on the down stairs before descending.
+pub fn try_next_level(ecs: &mut World) -> bool {
+    let player_pos = ecs.fetch::<Point>();
+    let map = ecs.fetch::<Map>();
+    let player_idx = map.xy_idx(player_pos.x, player_pos.y);
+    if map.tiles[player_idx] == TileType::DownStairs {
+        true
+    } else {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog.log(LogCategory::System, "There is no way down from here.");
+        false
+    }
+}
+
+/// Picks up an item from the player's tile. Returns false if there is nothing there.
+pub fn try_pickup_item(ecs: &mut World) -> bool {
+    let player_pos = *ecs.fetch::<Point>();
+    let player_entity = *ecs.fetch::<Entity>();
+    let map = ecs.fetch::<Map>();
+    let items = ecs.read_storage::<Item>();
+
+    let idx = map.xy_idx(player_pos.x, player_pos.y);
+    let target_item = map.tile_content[idx]
+        .iter()
+        .find(|entity| items.get(**entity).is_some());
+
+    match target_item {
+        None => {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.log(LogCategory::Loot, "There is nothing here to pick up.");
+            false
+        }
+        Some(item) => {
+            let mut pickup = ecs.write_storage::<WantsToPickupItem>();
+            pickup
+                .insert(
+                    player_entity,
+                    WantsToPickupItem {
+                        collected_by: player_entity,
+                        item: *item,
+                    },
+                )
+                .expect("Unable to insert want to pickup");
+            true
+        }
+    }
+}
+
+/// True if `item` is in the player's backpack.
+fn is_carried_by_player(ecs: &World, item: Entity) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let backpack = ecs.read_storage::<InBackpack>();
+    backpack
+        .get(item)
+        .is_some_and(|carried| carried.owner == player_entity)
+}
+
+/// The tiles the player can see that are no more than `range` away: the ones they can target.
+pub fn tiles_in_range(ecs: &World, range: i32) -> Vec<Point> {
+    let player_entity = *ecs.fetch::<Entity>();
+    let player_pos = *ecs.fetch::<Point>();
+    let viewsheds = ecs.read_storage::<Viewshed>();
+
+    match viewsheds.get(player_entity) {
+        None => Vec::new(),
+        Some(viewshed) => viewshed
+            .visible_tiles
+            .iter()
+            .filter(|tile| DistanceAlg::Pythagoras.distance2d(player_pos, **tile) <= range as f32)
+            .copied()
+            .collect(),
+    }
+}
+
+/// Uses an item from the player's backpack. Ranged items need a `target` within range, which
+/// other items ignore. Returns false if it can't be used.
+pub fn try_use_item(ecs: &mut World, item: Entity, target: Option<Point>) -> bool {
+    if !is_carried_by_player(ecs, item) {
+        return false;
+    }
+    let item_name = ecs.read_storage::<Name>().get(item).unwrap().name.clone();
+
+    // Only items that do something can be used
+    let usable = ecs.read_storage::<ProvidesHealing>().contains(item)
+        || ecs.read_storage::<RestoresMana>().contains(item)
+        || ecs.read_storage::<IncreasesMaxHp>().contains(item)
+        || ecs.read_storage::<InflictsDamage>().contains(item);
+    if !usable {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog.log(
+            LogCategory::Loot,
+            format!("You can't use the {}.", item_name),
+        );
+        return false;
+    }
+
+    let range = ecs
+        .read_storage::<Ranged>()
+        .get(item)
+        .map(|ranged| ranged.range);
+    let target = match (range, target) {
+        (None, _) => None,
+        (Some(range), Some(target)) if tiles_in_range(ecs, range).contains(&target) => Some(target),
+        (Some(_), _) => {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.log(
+                LogCategory::Loot,
+                format!(
+                    "You need a target in sight and in range to use the {}.",
+                    item_name
+                ),
+            );
+            return false;
+        }
+    };
+
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut wants_use = ecs.write_storage::<WantsToUseItem>();
+    wants_use
+        .insert(player_entity, WantsToUseItem { item, target })
+        .expect("Unable to insert want to use");
+    true
+}
+
+/// Drops an item from the player's backpack where they stand. Returns false if they aren't
+/// carrying it.
+pub fn try_drop_item(ecs: &mut World, item: Entity) -> bool {
+    if !is_carried_by_player(ecs, item) {
+        return false;
+    }
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut wants_drop = ecs.write_storage::<WantsToDropItem>();
+    wants_drop
+        .insert(player_entity, WantsToDropItem { item })
+        .expect("Unable to insert want to drop");
+    true
+}
+
+/// Casts the `spell`th of the player's known spells. Spells with a range need a `target` within
+/// range, which other spells ignore. Returns false if it can't be cast, such as when the player
+/// hasn't enough mana.
+pub fn try_cast_spell(ecs: &mut World, spell: usize, target: Option<Point>) -> bool {
+    let player_entity = *ecs.fetch::<Entity>();
+    let spell = match ecs
+        .read_storage::<KnownSpells>()
+        .get(player_entity)
+        .and_then(|known| known.spells.get(spell))
+    {
+        Some(spell) => spell.clone(),
+        None => return false,
+    };
+
+    let mana = ecs
+        .read_storage::<CombatStats>()
+        .get(player_entity)
+        .map_or(0, |stats| stats.curr_mana);
+    if mana < spell.mana_cost {
+        let mut gamelog = ecs.fetch_mut::<GameLog>();
+        gamelog.log(
+            LogCategory::Combat,
+            format!("You don't have enough mana to cast {}.", spell.name),
+        );
+        return false;
+    }
+
+    let target = match (spell.range, target) {
+        (None, _) => None,
+        (Some(range), Some(target)) if tiles_in_range(ecs, range).contains(&target) => Some(target),
+        (Some(_), _) => {
+            let mut gamelog = ecs.fetch_mut::<GameLog>();
+            gamelog.log(
+                LogCategory::Combat,
+                format!(
+                    "You need a target in sight and in range to cast {}.",
+                    spell.name
+                ),
+            );
+            return false;
+        }
+    };
+
+    let mut wants_cast = ecs.write_storage::<WantsToCastSpell>();
+    wants_cast
+        .insert(player_entity, WantsToCastSpell { spell, target })
+        .expect("Unable to insert want to cast");
+    true
+}
+
+/// Everything the player can do on their turn. Commands come from the keyboard in the game
+/// itself, but can just as well be sent by tests or bots with `State::submit`.
+#[derive(PartialEq, Copy, Clone, Debug)]
+pub enum PlayerCommand {
+    /// Move by one tile, attacking or opening whatever is in the way.
+    Move {
+        delta_x: i32,
+        delta_y: i32,
+    },
+    CloseDoors,
+    /// Pick up an item from the floor the player is standing on.
+    PickUp,
+    /// Use an item from the backpack. Ranged items are used on `target`.
+    UseItem {
+        item: Entity,
+        target: Option<Point>,
+    },
+    /// Drop an item from the backpack onto the floor.
+    DropItem {
+        item: Entity,
+    },
+    /// Cast the `spell`th known spell. Spells with a range are cast on `target`.
+    CastSpell {
+        spell: usize,
+        target: Option<Point>,
+    },
+    Descend,
+    SaveGame,
+    LoadGame,
+    /// Open the message history.
+    ShowLog,
+    /// Open the inventory, to pick an item to use or drop.
+    ShowInventory,
+    /// Open the spellbook, to pick a spell to cast.
+    ShowSpellbook,
+    /// Open the wizard console.
+    #[cfg(feature = "wizard")]
+    WizardConsole,
+}
+
+/// Carries out a command, returning the state the game should move on to.
+/// Commands that turn out to do nothing don't use up the player's turn.
+pub fn handle_command(ecs: &mut World, command: PlayerCommand) -> RunState {
+    match command {
+        PlayerCommand::Move { delta_x, delta_y } => match try_move_player(delta_x, delta_y, ecs) {
+            Some(cost) => spend_player_turn(ecs, cost),
+            None => return RunState::AwaitingInput,
+        },
+        PlayerCommand::CloseDoors => {
+            if !try_close_doors(ecs) {
+                return RunState::AwaitingInput;
+            }
+            spend_player_turn(ecs, ACTION_COST);
+        }
+        PlayerCommand::PickUp => {
+            if !try_pickup_item(ecs) {
+                return RunState::AwaitingInput;
+            }
+            spend_player_turn(ecs, ACTION_COST);
+        }
+        PlayerCommand::UseItem { item, target } => {
+            if !try_use_item(ecs, item, target) {
+                return RunState::AwaitingInput;
+            }
+            spend_player_turn(ecs, ACTION_COST);
+        }
+        PlayerCommand::DropItem { item } => {
+            if !try_drop_item(ecs, item) {
+                return RunState::AwaitingInput;
+            }
+            spend_player_turn(ecs, ACTION_COST);
+        }
+        PlayerCommand::CastSpell { spell, target } => {
+            if !try_cast_spell(ecs, spell, target) {
+                return RunState::AwaitingInput;
+            }
+            spend_player_turn(ecs, ACTION_COST);
+        }
+
+        // Saving, loading and opening the menus don't take up a turn
+        PlayerCommand::SaveGame => return RunState::SaveGame,
+        PlayerCommand::LoadGame => return RunState::LoadGame,
+        PlayerCommand::ShowLog => {
+            return RunState::MessageLog {
+                scroll: 0,
+                filter: None,
+            }
+        }
+        PlayerCommand::ShowInventory => return RunState::ShowInventory,
+        PlayerCommand::ShowSpellbook => return RunState::ShowSpellbook,
+        #[cfg(feature = "wizard")]
+        PlayerCommand::WizardConsole => return RunState::WizardConsole,
+
+        PlayerCommand::Descend => {
+            if try_next_level(ecs) {
+                spend_player_turn(ecs, ACTION_COST);
+                return RunState::NextLevel;
+            }
+            return RunState::AwaitingInput;
         }
     }
+    RunState::Ticking
+}
+
+fn spend_player_turn(ecs: &mut World, cost: i32) {
+    let player_entity = *ecs.fetch::<Entity>();
+    let mut energies = ecs.write_storage::<Energy>();
+    let mut turns = ecs.write_storage::<MyTurn>();
+    spend_turn(&mut energies, &mut turns, player_entity, cost);
+
+    // Whatever happens until the player's next move is logged as part of this turn
+    ecs.write_resource::<GameLog>().turn += 1;
 }
 
-pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
+/// Translates the key pressed this frame into a command, if it means anything.
+pub fn player_input(ctx: &mut Rltk) -> Option<PlayerCommand> {
     // Player movement
 
     let input_global = &rltk::INPUT;
@@ -62,40 +427,67 @@ pub fn player_input(gs: &mut State, ctx: &mut Rltk) -> RunState {
     let control_down = input_mutex.is_key_pressed(VirtualKeyCode::LControl)
         | input_mutex.is_key_pressed(VirtualKeyCode::RControl);
 
+    let step = |delta_x, delta_y| Some(PlayerCommand::Move { delta_x, delta_y });
+
     match ctx.key {
-        None => return RunState::AwaitingInput, // Nothing happened
+        None => None, // Nothing happened
         Some(key) => match (key, shift_down, control_down) {
-
             // Diagonals. Checked first because of control and shift modifiers
-            (VirtualKeyCode::Right, true, false) |(VirtualKeyCode::Numpad9, ..) | (VirtualKeyCode::U, ..) => try_move_player(1, -1, &mut gs.ecs),
+            (VirtualKeyCode::Right, true, false)
+            | (VirtualKeyCode::Numpad9, ..)
+            | (VirtualKeyCode::U, ..) => step(1, -1),
 
-            (VirtualKeyCode::Left, true, false) |(VirtualKeyCode::Numpad7, ..) | (VirtualKeyCode::Y, ..) => try_move_player(-1, -1, &mut gs.ecs),
+            (VirtualKeyCode::Left, true, false)
+            | (VirtualKeyCode::Numpad7, ..)
+            | (VirtualKeyCode::Y, ..) => step(-1, -1),
 
-            (VirtualKeyCode::Right, false, true) | (VirtualKeyCode::Numpad3, ..) | (VirtualKeyCode::N, ..) => try_move_player(1, 1, &mut gs.ecs),
+            (VirtualKeyCode::Right, false, true)
+            | (VirtualKeyCode::Numpad3, ..)
+            | (VirtualKeyCode::N, ..) => step(1, 1),
 
-            (VirtualKeyCode::Left, false, true) |(VirtualKeyCode::Numpad1, ..) | (VirtualKeyCode::B, ..) => try_move_player(-1, 1, &mut gs.ecs),
+            (VirtualKeyCode::Left, false, true)
+            | (VirtualKeyCode::Numpad1, ..)
+            | (VirtualKeyCode::B, ..) => step(-1, 1),
 
             // Cardinal directions
-            (VirtualKeyCode::Left, ..) | (VirtualKeyCode::Numpad4, ..) | (VirtualKeyCode::H, ..) => {
-                try_move_player(-1, 0, &mut gs.ecs)
-            }
+            (VirtualKeyCode::Left, ..)
+            | (VirtualKeyCode::Numpad4, ..)
+            | (VirtualKeyCode::H, ..) => step(-1, 0),
 
-            (VirtualKeyCode::Right, ..) | (VirtualKeyCode::Numpad6, ..) | (VirtualKeyCode::L, ..) => {
-                try_move_player(1, 0, &mut gs.ecs)
-            }
+            (VirtualKeyCode::Right, ..)
+            | (VirtualKeyCode::Numpad6, ..)
+            | (VirtualKeyCode::L, ..) => step(1, 0),
 
-            (VirtualKeyCode::Up, ..) | (VirtualKeyCode::Numpad8,..) | (VirtualKeyCode::K, ..) => {
-                try_move_player(0, -1, &mut gs.ecs)
+            (VirtualKeyCode::Up, ..) | (VirtualKeyCode::Numpad8, ..) | (VirtualKeyCode::K, ..) => {
+                step(0, -1)
             }
 
-            (VirtualKeyCode::Down, ..) | (VirtualKeyCode::Numpad2, ..) | (VirtualKeyCode::J, ..) => {
-                try_move_player(0, 1, &mut gs.ecs)
-            }
+            (VirtualKeyCode::Down, ..)
+            | (VirtualKeyCode::Numpad2, ..)
+            | (VirtualKeyCode::J, ..) => step(0, 1),
+
+            // Doors
+            (VirtualKeyCode::C, ..) => Some(PlayerCommand::CloseDoors),
+
+            // Items
+            (VirtualKeyCode::G, ..) | (VirtualKeyCode::Comma, ..) => Some(PlayerCommand::PickUp),
+            (VirtualKeyCode::I, ..) => Some(PlayerCommand::ShowInventory),
+
+            // Spells
+            (VirtualKeyCode::Z, ..) => Some(PlayerCommand::ShowSpellbook),
+
+            (VirtualKeyCode::F5, ..) => Some(PlayerCommand::SaveGame),
+            (VirtualKeyCode::F9, ..) => Some(PlayerCommand::LoadGame),
+
+            (VirtualKeyCode::M, ..) => Some(PlayerCommand::ShowLog),
+
+            #[cfg(feature = "wizard")]
+            (VirtualKeyCode::Grave, ..) => Some(PlayerCommand::WizardConsole),
 
-            
+            // Level changes
+            (VirtualKeyCode::Period, ..) => Some(PlayerCommand::Descend),
 
-            _ => return RunState::AwaitingInput,
+            _ => None,
         },
     }
-    RunState::PlayerTurn
 }
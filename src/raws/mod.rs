@@ -0,0 +1,178 @@
+use super::{
+    initiative_system::{ACTION_COST, NORMAL_SPEED},
+    random_table::RandomTable,
+    BlocksTile, CombatStats, Consumable, Description, Energy, IncreasesMaxHp, InflictsDamage, Item,
+    Monster, Name, Position, ProvidesHealing, Ranged, Renderable, RestoresMana, SerializeMe, Spell,
+    Viewshed,
+};
+use crate::resources::read_resource;
+use serde::{de::DeserializeOwned, Deserialize};
+
+mod rawmaster;
+pub use rawmaster::{spawn_named_entity, RawMaster};
+
+/// Monster, item and spell definitions, and where things spawn, are read from `resources/raws`
+/// when the game starts, so they can be tuned without touching the code. Copies are built in too.
+const MONSTERS: &str = include_str!("../../resources/raws/monsters.json");
+const ITEMS: &str = include_str!("../../resources/raws/items.json");
+const SPELLS: &str = include_str!("../../resources/raws/spells.json");
+const SPAWN_TABLE: &str = include_str!("../../resources/raws/spawn_table.json");
+
+#[derive(Debug)]
+pub struct Raws {
+    pub mobs: Vec<Mob>,
+    pub items: Vec<RawItem>,
+    pub spells: Vec<RawSpell>,
+    pub spawn_table: Vec<SpawnTableEntry>,
+}
+
+#[derive(Deserialize)]
+struct MonsterFile {
+    mobs: Vec<Mob>,
+}
+
+#[derive(Deserialize)]
+struct ItemFile {
+    items: Vec<RawItem>,
+}
+
+#[derive(Deserialize)]
+struct SpellFile {
+    spells: Vec<RawSpell>,
+}
+
+#[derive(Deserialize)]
+struct SpawnTableFile {
+    spawn_table: Vec<SpawnTableEntry>,
+}
+
+/// A template that monsters are built from.
+#[derive(Deserialize, Debug)]
+pub struct Mob {
+    pub name: String,
+    pub renderable: RawRenderable,
+    pub vision_range: i32,
+    /// How quickly it gains energy to act with. Normal speed if left out.
+    #[serde(default = "normal_speed")]
+    pub speed: i32,
+    /// The energy a melee attack takes. Heavy hitters take longer than other actions.
+    #[serde(default = "action_cost")]
+    pub attack_cost: i32,
+    pub stats: MobStats,
+    #[serde(default)]
+    pub flags: Vec<MobFlag>,
+}
+
+/// A template that items are built from.
+#[derive(Deserialize, Debug)]
+pub struct RawItem {
+    pub name: String,
+    /// Shown when the player examines it.
+    pub description: String,
+    pub renderable: RawRenderable,
+    /// Used up when it is used.
+    #[serde(default)]
+    pub consumable: bool,
+    /// Items with a range are used on a tile the player picks, rather than on the player.
+    #[serde(default)]
+    pub range: Option<i32>,
+    #[serde(default)]
+    pub effects: ItemEffects,
+}
+
+/// What happens when an item is used. An item can have any number of these, and can't be used
+/// at all with none of them.
+#[derive(Deserialize, Debug, Default)]
+pub struct ItemEffects {
+    pub provides_healing: Option<i32>,
+    pub restores_mana: Option<i32>,
+    pub increases_max_hp: Option<i32>,
+    pub inflicts_damage: Option<i32>,
+}
+
+/// A template for a spell. The player knows every spell there is.
+#[derive(Deserialize, Debug)]
+pub struct RawSpell {
+    pub name: String,
+    /// Shown in the spellbook.
+    pub description: String,
+    pub mana_cost: i32,
+    /// Spells with a range are cast on a tile the player picks, rather than on the player.
+    #[serde(default)]
+    pub range: Option<i32>,
+    /// Spells with a radius also hit everyone that far from the target. Needs a range.
+    #[serde(default)]
+    pub radius: i32,
+    pub effects: SpellEffects,
+}
+
+/// What happens to everyone a spell hits.
+#[derive(Deserialize, Debug, Default)]
+pub struct SpellEffects {
+    pub damage: Option<i32>,
+    pub healing: Option<i32>,
+    pub slows: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct RawRenderable {
+    /// A single character, converted to cp437.
+    pub glyph: char,
+    /// Colours are written as `#RRGGBB`.
+    pub fg: String,
+    pub bg: String,
+}
+
+/// Hit points and mana start out full, so only the maximums are given.
+#[derive(Deserialize, Debug)]
+pub struct MobStats {
+    pub max_hp: i32,
+    pub defense: i32,
+    pub magic_res: i32,
+    pub power: i32,
+    pub max_mana: i32,
+}
+
+fn normal_speed() -> i32 {
+    NORMAL_SPEED
+}
+
+fn action_cost() -> i32 {
+    ACTION_COST
+}
+
+/// Marker components a monster may have.
+#[derive(Deserialize, Debug, PartialEq, Copy, Clone)]
+pub enum MobFlag {
+    BlocksTile,
+}
+
+/// How likely a monster or item is to spawn, and on which levels. Depths are inclusive.
+#[derive(Deserialize, Debug)]
+pub struct SpawnTableEntry {
+    pub name: String,
+    pub weight: i32,
+    pub min_depth: i32,
+    /// No limit if left out.
+    #[serde(default)]
+    pub max_depth: Option<i32>,
+}
+
+impl Raws {
+    /// Reads and parses the raw files. They are checked when the game starts, so any mistake in
+    /// them is reported straight away.
+    pub fn load() -> Raws {
+        Raws {
+            mobs: parse::<MonsterFile>("resources/raws/monsters.json", MONSTERS).mobs,
+            items: parse::<ItemFile>("resources/raws/items.json", ITEMS).items,
+            spells: parse::<SpellFile>("resources/raws/spells.json", SPELLS).spells,
+            spawn_table: parse::<SpawnTableFile>("resources/raws/spawn_table.json", SPAWN_TABLE)
+                .spawn_table,
+        }
+    }
+}
+
+fn parse<T: DeserializeOwned>(path: &str, embedded: &'static str) -> T {
+    serde_json::from_str(&read_resource(path, embedded))
+        .unwrap_or_else(|e| panic!("Unable to parse {}: {}", path, e))
+}
@@ -0,0 +1,281 @@
+use super::{
+    BlocksTile, CombatStats, Consumable, Description, Energy, IncreasesMaxHp, InflictsDamage, Item,
+    Mob, MobFlag, Monster, Name, Position, ProvidesHealing, RandomTable, Ranged, RawItem, Raws,
+    Renderable, RestoresMana, SerializeMe, Spell, Viewshed,
+};
+use rltk::RGB;
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+use std::collections::{HashMap, HashSet};
+
+/// The parsed raws, with an index to look templates up by name. Stored as a resource in the World.
+pub struct RawMaster {
+    raws: Raws,
+    mob_index: HashMap<String, usize>,
+    item_index: HashMap<String, usize>,
+    glyph_index: HashMap<char, String>,
+}
+
+impl RawMaster {
+    pub fn load() -> RawMaster {
+        let raws = Raws::load();
+        let mut mob_index = HashMap::new();
+        for (i, mob) in raws.mobs.iter().enumerate() {
+            if mob_index.insert(mob.name.clone(), i).is_some() {
+                panic!("Monster '{}' is defined more than once", mob.name);
+            }
+            // Catch bad colours now, rather than the first time the monster spawns
+            parse_colour(&mob.renderable.fg, &mob.name);
+            parse_colour(&mob.renderable.bg, &mob.name);
+        }
+
+        // Prefabs and the spawn table only give a name, so it can't be both a monster and an item
+        let mut item_index = HashMap::new();
+        for (i, item) in raws.items.iter().enumerate() {
+            if mob_index.contains_key(&item.name)
+                || item_index.insert(item.name.clone(), i).is_some()
+            {
+                panic!("'{}' is defined more than once", item.name);
+            }
+            parse_colour(&item.renderable.fg, &item.name);
+            parse_colour(&item.renderable.bg, &item.name);
+        }
+
+        let mut spell_names = HashSet::new();
+        for spell in raws.spells.iter() {
+            if !spell_names.insert(spell.name.clone()) {
+                panic!("Spell '{}' is defined more than once", spell.name);
+            }
+            if spell.radius > 0 && spell.range.is_none() {
+                panic!("Spell '{}' has a radius but no range", spell.name);
+            }
+        }
+
+        for entry in raws.spawn_table.iter() {
+            if !mob_index.contains_key(&entry.name) && !item_index.contains_key(&entry.name) {
+                panic!("Spawn table refers to unknown '{}'", entry.name);
+            }
+        }
+
+        // Where several things share a glyph, it stands for the first of them, monsters first
+        let mut glyph_index = HashMap::new();
+        let mobs = raws
+            .mobs
+            .iter()
+            .map(|mob| (mob.renderable.glyph, &mob.name));
+        let items = raws
+            .items
+            .iter()
+            .map(|item| (item.renderable.glyph, &item.name));
+        for (glyph, name) in mobs.chain(items) {
+            glyph_index.entry(glyph).or_insert_with(|| name.clone());
+        }
+
+        RawMaster {
+            raws,
+            mob_index,
+            item_index,
+            glyph_index,
+        }
+    }
+
+    /// The monsters that may spawn at the given depth, weighted by how common they are.
+    pub fn monster_spawn_table(&self, depth: i32) -> RandomTable {
+        self.spawn_table(depth, |name| self.mob_index.contains_key(name))
+    }
+
+    /// The items that may spawn at the given depth, weighted by how common they are.
+    pub fn item_spawn_table(&self, depth: i32) -> RandomTable {
+        self.spawn_table(depth, |name| self.item_index.contains_key(name))
+    }
+
+    fn spawn_table<F: Fn(&str) -> bool>(&self, depth: i32, include: F) -> RandomTable {
+        self.raws
+            .spawn_table
+            .iter()
+            .filter(|entry| {
+                depth >= entry.min_depth && entry.max_depth.is_none_or(|max| depth <= max)
+            })
+            .filter(|entry| include(&entry.name))
+            .fold(RandomTable::new(), |table, entry| {
+                table.add(&entry.name, entry.weight)
+            })
+    }
+
+    pub fn get_mob(&self, name: &str) -> Option<&Mob> {
+        self.mob_index.get(name).map(|i| &self.raws.mobs[*i])
+    }
+
+    pub fn get_item(&self, name: &str) -> Option<&RawItem> {
+        self.item_index.get(name).map(|i| &self.raws.items[*i])
+    }
+
+    /// The name of the monster or item drawn with `glyph`, such as a prefab's inhabitants.
+    pub fn name_for_glyph(&self, glyph: char) -> Option<&str> {
+        self.glyph_index.get(&glyph).map(|name| name.as_str())
+    }
+
+    /// Every spell, in the order they are defined, ready to be learnt.
+    pub fn spells(&self) -> Vec<Spell> {
+        self.raws
+            .spells
+            .iter()
+            .map(|spell| Spell {
+                name: spell.name.clone(),
+                description: spell.description.clone(),
+                mana_cost: spell.mana_cost,
+                range: spell.range,
+                radius: spell.radius,
+                damage: spell.effects.damage,
+                healing: spell.effects.healing,
+                slows: spell.effects.slows,
+            })
+            .collect()
+    }
+
+    /// The name of every monster, then every item, in the order they are defined.
+    #[cfg(feature = "wizard")]
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        let mobs = self.raws.mobs.iter().map(|mob| mob.name.as_str());
+        let items = self.raws.items.iter().map(|item| item.name.as_str());
+        mobs.chain(items)
+    }
+}
+
+fn parse_colour(hex: &str, name: &str) -> RGB {
+    RGB::from_hex(hex).unwrap_or_else(|_| panic!("'{}' has a bad colour '{}'", name, hex))
+}
+
+/// Builds a monster from its template, standing on `position`. Monsters are numbered by their
+/// entity, so that two goblins can be told apart. Returns `None` if there is no template with that
+/// name.
+pub fn spawn_named_mob(ecs: &mut World, key: &str, position: Position) -> Option<Entity> {
+    let (mob_name, renderable, viewshed, stats, energy, blocks_tile) = {
+        let raws = ecs.fetch::<RawMaster>();
+        let mob = raws.get_mob(key)?;
+
+        let renderable = Renderable {
+            glyph: rltk::to_cp437(mob.renderable.glyph),
+            fg: parse_colour(&mob.renderable.fg, &mob.name),
+            bg: parse_colour(&mob.renderable.bg, &mob.name),
+            render_order: 1,
+        };
+        let viewshed = Viewshed {
+            visible_tiles: Vec::new(),
+            range: mob.vision_range,
+            dirty: true,
+        };
+        let stats = CombatStats {
+            max_hp: mob.stats.max_hp,
+            curr_hp: mob.stats.max_hp,
+            defense: mob.stats.defense,
+            magic_res: mob.stats.magic_res,
+            power: mob.stats.power,
+            max_mana: mob.stats.max_mana,
+            curr_mana: mob.stats.max_mana,
+        };
+        let energy = Energy {
+            speed: mob.speed,
+            energy: 0,
+            attack_cost: mob.attack_cost,
+        };
+        let blocks_tile = mob.flags.contains(&MobFlag::BlocksTile);
+        (
+            mob.name.clone(),
+            renderable,
+            viewshed,
+            stats,
+            energy,
+            blocks_tile,
+        )
+    };
+
+    let mut builder = ecs
+        .create_entity()
+        .with(position)
+        .with(renderable)
+        .with(viewshed)
+        .with(Monster {})
+        .with(stats)
+        .with(energy);
+    if blocks_tile {
+        builder = builder.with(BlocksTile {});
+    }
+    let entity = builder.marked::<SimpleMarker<SerializeMe>>().build();
+
+    ecs.write_storage::<Name>()
+        .insert(
+            entity,
+            Name {
+                name: format!("{} #{}", mob_name, entity.id()),
+            },
+        )
+        .expect("Unable to name monster");
+    Some(entity)
+}
+
+/// Builds an item from its template, lying on the floor at `position`. Returns `None` if there is
+/// no template with that name.
+pub fn spawn_named_item(ecs: &mut World, key: &str, position: Position) -> Option<Entity> {
+    let (item_name, description, renderable, consumable, range, effects) = {
+        let raws = ecs.fetch::<RawMaster>();
+        let item = raws.get_item(key)?;
+
+        let renderable = Renderable {
+            glyph: rltk::to_cp437(item.renderable.glyph),
+            fg: parse_colour(&item.renderable.fg, &item.name),
+            bg: parse_colour(&item.renderable.bg, &item.name),
+            render_order: 2,
+        };
+        (
+            item.name.clone(),
+            item.description.clone(),
+            renderable,
+            item.consumable,
+            item.range,
+            (
+                item.effects.provides_healing,
+                item.effects.restores_mana,
+                item.effects.increases_max_hp,
+                item.effects.inflicts_damage,
+            ),
+        )
+    };
+
+    let mut builder = ecs
+        .create_entity()
+        .with(position)
+        .with(renderable)
+        .with(Name { name: item_name })
+        .with(Description { text: description })
+        .with(Item {});
+    if consumable {
+        builder = builder.with(Consumable {});
+    }
+    if let Some(range) = range {
+        builder = builder.with(Ranged { range });
+    }
+    let (healing, mana, max_hp, damage) = effects;
+    if let Some(heal_amount) = healing {
+        builder = builder.with(ProvidesHealing { heal_amount });
+    }
+    if let Some(mana_amount) = mana {
+        builder = builder.with(RestoresMana { mana_amount });
+    }
+    if let Some(amount) = max_hp {
+        builder = builder.with(IncreasesMaxHp { amount });
+    }
+    if let Some(damage) = damage {
+        builder = builder.with(InflictsDamage { damage });
+    }
+    Some(builder.marked::<SimpleMarker<SerializeMe>>().build())
+}
+
+/// Builds whatever has the name `key`, monster or item. Returns `None` if there is no such thing.
+pub fn spawn_named_entity(ecs: &mut World, key: &str, position: Position) -> Option<Entity> {
+    if ecs.fetch::<RawMaster>().get_mob(key).is_some() {
+        spawn_named_mob(ecs, key, position)
+    } else {
+        spawn_named_item(ecs, key, position)
+    }
+}
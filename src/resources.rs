@@ -0,0 +1,8 @@
+use std::fs;
+
+/// Reads a data file from the `resources` directory, so that it can be changed without rebuilding
+/// the game. When it can't be read (such as when the game is run from another directory), the
+/// copy built into the game is used instead.
+pub fn read_resource(path: &str, embedded: &'static str) -> String {
+    fs::read_to_string(path).unwrap_or_else(|_| embedded.to_string())
+}
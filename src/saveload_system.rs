@@ -0,0 +1,200 @@
+use super::components::*;
+use super::{gamelog::GameLog, register_components, GameSeed, Map};
+use rltk::{Point, RandomNumberGenerator};
+use specs::error::NoError;
+use specs::prelude::*;
+use specs::saveload::{
+    DeserializeComponents, MarkedBuilder, SerializeComponents, SimpleMarker, SimpleMarkerAllocator,
+};
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+
+const SAVE_PATH: &str = "./savegame.json";
+/// Saves are written here first, and moved over the old save once complete.
+const SAVE_TEMP_PATH: &str = "./savegame.json.tmp";
+
+macro_rules! serialize_individually {
+    ($ecs:expr, $ser:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        SerializeComponents::<NoError, SimpleMarker<SerializeMe>>::serialize(
+            &( $ecs.read_storage::<$type>(), ),
+            &$data.0,
+            &$data.1,
+            &mut $ser,
+        )
+        .map_err(|e| e.to_string())?;
+        )*
+    };
+}
+
+macro_rules! deserialize_individually {
+    ($ecs:expr, $de:expr, $data:expr, $( $type:ty),*) => {
+        $(
+        DeserializeComponents::<NoError, _>::deserialize(
+            &mut ( &mut $ecs.write_storage::<$type>(), ),
+            &mut $data.0, // entities
+            &mut $data.1, // marker
+            &mut $data.2, // allocator
+            &mut $de,
+        )
+        .map_err(|e| e.to_string())?;
+        )*
+    };
+}
+
+/// Every component that is saved, passed on to `$mac` after its other arguments. Both writing
+/// and reading a save go through this, so they always agree on what is in it.
+macro_rules! with_saved_components {
+    ($mac:ident!($($args:tt)*)) => {
+        $mac!(
+            $($args)*,
+            Position,
+            Renderable,
+            Player,
+            Viewshed,
+            Monster,
+            Name,
+            BlocksTile,
+            CombatStats,
+            WantsToMelee,
+            SufferDamage,
+            KilledBy,
+            Energy,
+            MyTurn,
+            Invulnerable,
+            Item,
+            InBackpack,
+            WantsToPickupItem,
+            WantsToDropItem,
+            Description,
+            WantsToUseItem,
+            Consumable,
+            ProvidesHealing,
+            RestoresMana,
+            IncreasesMaxHp,
+            Ranged,
+            InflictsDamage,
+            KnownSpells,
+            WantsToCastSpell,
+            Slowed,
+            SerializationHelper
+        )
+    };
+}
+
+pub fn does_save_exist() -> bool {
+    Path::new(SAVE_PATH).exists()
+}
+
+/// Writes every marked entity, along with the map, log and random number generator, to the
+/// save file. The old save is only replaced once the new one has been written in full.
+pub fn save_game(ecs: &mut World) -> Result<(), String> {
+    // Resources aren't serialized, so they are carried by a temporary entity
+    let helper = SerializationHelper {
+        map: (*ecs.fetch::<Map>()).clone(),
+        log: (*ecs.fetch::<GameLog>()).clone(),
+        rng: (*ecs.fetch::<RandomNumberGenerator>()).clone(),
+        seed: ecs.fetch::<GameSeed>().seed,
+    };
+    let savehelper = ecs
+        .create_entity()
+        .with(helper)
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build();
+
+    let result = write_entities(ecs);
+
+    ecs.delete_entity(savehelper)
+        .expect("Unable to delete save helper");
+    result
+}
+
+fn write_entities(ecs: &World) -> Result<(), String> {
+    {
+        let data = (
+            ecs.entities(),
+            ecs.read_storage::<SimpleMarker<SerializeMe>>(),
+        );
+
+        let writer = File::create(SAVE_TEMP_PATH).map_err(|e| e.to_string())?;
+        let mut serializer = serde_json::Serializer::new(writer);
+        with_saved_components!(serialize_individually!(ecs, serializer, data));
+    }
+    fs::rename(SAVE_TEMP_PATH, SAVE_PATH).map_err(|e| e.to_string())
+}
+
+/// Replaces everything in the World with the contents of the save file. The file is read into a
+/// scratch World first, so a damaged save, or one from a build with different components, leaves
+/// the World as it was.
+pub fn load_game(ecs: &mut World) -> Result<(), String> {
+    let data = fs::read_to_string(SAVE_PATH).map_err(|e| e.to_string())?;
+
+    let mut scratch = World::new();
+    register_components(&mut scratch);
+    scratch.insert(SimpleMarkerAllocator::<SerializeMe>::new());
+    read_entities(&mut scratch, &data)?;
+    saved_state(&scratch)?;
+
+    {
+        let to_delete: Vec<Entity> = ecs.entities().join().collect();
+        for entity in to_delete.iter() {
+            ecs.delete_entity(*entity).expect("Deletion failed");
+        }
+    }
+    read_entities(ecs, &data)?;
+
+    // The World may be brand new (continuing from the main menu), so the resources are inserted
+    // rather than overwritten
+    let (helper_entity, helper, player_entity, player_pos) = saved_state(ecs)?;
+    let mut worldmap = helper.map;
+    worldmap.tile_content = vec![Vec::new(); (worldmap.width * worldmap.height) as usize];
+    ecs.insert(worldmap);
+    ecs.insert(helper.log);
+    ecs.insert(helper.rng);
+    ecs.insert(GameSeed { seed: helper.seed });
+    ecs.insert(player_pos);
+    ecs.insert(player_entity);
+
+    ecs.delete_entity(helper_entity)
+        .expect("Unable to delete save helper");
+    Ok(())
+}
+
+/// Reads the saved entities in `data` into `ecs`.
+fn read_entities(ecs: &mut World, data: &str) -> Result<(), String> {
+    let mut de = serde_json::Deserializer::from_str(data);
+    {
+        let mut d = (
+            &mut ecs.entities(),
+            &mut ecs.write_storage::<SimpleMarker<SerializeMe>>(),
+            &mut ecs.write_resource::<SimpleMarkerAllocator<SerializeMe>>(),
+        );
+        with_saved_components!(deserialize_individually!(ecs, de, d));
+    }
+    // Anything left over means the save was written with other components
+    de.end().map_err(|e| e.to_string())
+}
+
+/// Finds the entity carrying the saved resources, and the player, in a freshly loaded World.
+fn saved_state(ecs: &World) -> Result<(Entity, SerializationHelper, Entity, Point), String> {
+    let entities = ecs.entities();
+    let helpers = ecs.read_storage::<SerializationHelper>();
+    let players = ecs.read_storage::<Player>();
+    let positions = ecs.read_storage::<Position>();
+
+    let (helper_entity, helper) = (&entities, &helpers)
+        .join()
+        .next()
+        .ok_or("The save file has no map")?;
+    let (player_entity, _player, pos) = (&entities, &players, &positions)
+        .join()
+        .next()
+        .ok_or("The save file has no player")?;
+    Ok((
+        helper_entity,
+        helper.clone(),
+        player_entity,
+        Point::new(pos.x, pos.y),
+    ))
+}
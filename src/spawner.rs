@@ -0,0 +1,117 @@
+use super::{
+    initiative_system::{ACTION_COST, NORMAL_SPEED},
+    raws,
+    raws::RawMaster,
+    CombatStats, Energy, KnownSpells, Map, Name, Player, Position, Renderable, SerializeMe,
+    Viewshed,
+};
+use rltk::{RandomNumberGenerator, RGB};
+use specs::prelude::*;
+use specs::saveload::{MarkedBuilder, SimpleMarker};
+use std::collections::HashSet;
+
+/// Spawns the player, knowing every spell in the raws, and returns their entity object.
+pub fn player(ecs: &mut World, player_x: i32, player_y: i32) -> Entity {
+    let spells = ecs.fetch::<RawMaster>().spells();
+    ecs.create_entity()
+        .with(Position {
+            x: player_x,
+            y: player_y,
+        })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: RGB::named(rltk::YELLOW),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 0,
+        })
+        .with(Player {})
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 8,
+            dirty: true, // Force initial recompute
+        })
+        .with(Name {
+            name: "Player".to_string(),
+        })
+        .with(CombatStats {
+            max_hp: 30,
+            curr_hp: 30,
+            defense: 2,
+            magic_res: 4,
+            max_mana: 50,
+            curr_mana: 50,
+            power: 4,
+        })
+        .with(Energy {
+            speed: NORMAL_SPEED,
+            energy: ACTION_COST, // The player gets the first move
+            attack_cost: ACTION_COST,
+        })
+        .with(KnownSpells { spells })
+        .marked::<SimpleMarker<SerializeMe>>()
+        .build()
+}
+
+/// The most monsters a spawn region gets on the first level. Every other level down adds one more.
+const MAX_SPAWNS: i32 = 3;
+/// The most items a spawn region gets, on any level.
+const MAX_ITEMS: i32 = 2;
+
+/// Spawns a random number of monsters and items, picked from the spawn table for the current
+/// depth, on random tiles of a spawn region. No two share a tile: tiles in `occupied` are
+/// skipped, and the ones used here are added to it.
+pub fn spawn_region(ecs: &mut World, region: &[usize], occupied: &mut HashSet<usize>) {
+    let spawns: Vec<(usize, String)> = {
+        let depth = ecs.fetch::<Map>().depth;
+        let raws = ecs.fetch::<RawMaster>();
+        let mut rng = ecs.write_resource::<RandomNumberGenerator>();
+
+        let mut free_tiles: Vec<usize> = region
+            .iter()
+            .filter(|idx| !occupied.contains(idx))
+            .copied()
+            .collect();
+        let monster_count = i32::max(0, rng.roll_dice(1, MAX_SPAWNS + 1) - 1 + (depth - 1) / 2);
+        let item_count = rng.roll_dice(1, MAX_ITEMS + 1) - 1;
+
+        let mut spawns = Vec::new();
+        let tables = [
+            (raws.monster_spawn_table(depth), monster_count),
+            (raws.item_spawn_table(depth), item_count),
+        ];
+        for (table, count) in tables.iter() {
+            for _ in 0..*count {
+                if free_tiles.is_empty() {
+                    break;
+                }
+                let name = match table.roll(&mut rng) {
+                    Some(name) => name.to_string(),
+                    None => break,
+                };
+                let tile = (rng.roll_dice(1, free_tiles.len() as i32) - 1) as usize;
+                let idx = free_tiles.swap_remove(tile);
+                occupied.insert(idx);
+                spawns.push((idx, name));
+            }
+        }
+        spawns
+    };
+
+    for (idx, name) in spawns.iter() {
+        spawn_named(ecs, name, *idx);
+    }
+}
+
+/// Spawns a monster or item by the name of its template in the raws, such as one in a prefab.
+pub fn spawn_named(ecs: &mut World, name: &str, idx: usize) {
+    let position = {
+        let map = ecs.fetch::<Map>();
+        Position {
+            x: idx as i32 % map.width,
+            y: idx as i32 / map.width,
+        }
+    };
+    if raws::spawn_named_entity(ecs, name, position).is_none() {
+        panic!("Don't know how to spawn '{}'", name);
+    }
+}
@@ -0,0 +1,150 @@
+use super::{
+    gamelog::{GameLog, LogCategory, LogEntry},
+    CombatStats, Map, Name, Renderable, Slowed, SufferDamage, WantsToCastSpell,
+};
+use rltk::{field_of_view, Point, RGB};
+use specs::prelude::*;
+
+/// Spell names are shown in the log in this colour.
+const SPELL_COLOUR: (u8, u8, u8) = rltk::CYAN;
+
+/// Casts the spells someone wants to cast, paying their mana cost. Spells are cast on the
+/// caster, or on everything on the target tile of a spell with a range, and everyone within its
+/// radius. Magic damage is lessened by the `magic_res` of whoever it hits.
+pub struct SpellCastSystem {}
+
+impl<'a> System<'a> for SpellCastSystem {
+    #[allow(clippy::type_complexity)]
+    type SystemData = (
+        ReadExpect<'a, Entity>,
+        WriteExpect<'a, GameLog>,
+        ReadExpect<'a, Map>,
+        Entities<'a>,
+        WriteStorage<'a, WantsToCastSpell>,
+        ReadStorage<'a, Name>,
+        ReadStorage<'a, Renderable>,
+        WriteStorage<'a, CombatStats>,
+        WriteStorage<'a, SufferDamage>,
+        WriteStorage<'a, Slowed>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            player_entity,
+            mut gamelog,
+            map,
+            entities,
+            mut wants_cast,
+            names,
+            renderables,
+            mut combat_stats,
+            mut suffer_damage,
+            mut slowed,
+        ) = data;
+
+        for (entity, cast) in (&entities, &wants_cast).join() {
+            let spell = &cast.spell;
+            match combat_stats.get_mut(entity) {
+                Some(stats) if stats.curr_mana >= spell.mana_cost => {
+                    stats.curr_mana -= spell.mana_cost
+                }
+                _ => continue,
+            }
+
+            let targets: Vec<Entity> = match cast.target {
+                None => vec![entity],
+                Some(target) => {
+                    let tiles = if spell.radius > 0 {
+                        // The blast doesn't go through walls
+                        let mut tiles = field_of_view(target, spell.radius, &*map);
+                        tiles.retain(|p| {
+                            p.x >= 0 && p.x < map.width && p.y >= 0 && p.y < map.height
+                        });
+                        tiles
+                    } else {
+                        vec![target]
+                    };
+                    tiles
+                        .iter()
+                        .flat_map(|tile: &Point| {
+                            map.tile_content[map.xy_idx(tile.x, tile.y)].iter()
+                        })
+                        .filter(|target| combat_stats.contains(**target))
+                        .copied()
+                        .collect()
+                }
+            };
+
+            let caster_name = names.get(entity).map_or("something", |name| &name.name);
+            let mut effects: Vec<String> = Vec::new();
+            let mut hits: Vec<LogEntry> = Vec::new();
+            for target in targets.iter() {
+                let stats = combat_stats.get_mut(*target).unwrap();
+
+                if let Some(damage) = spell.damage {
+                    let damage = i32::max(0, damage - stats.magic_res);
+                    if damage == 0 {
+                        hits.push(
+                            LogEntry::new(LogCategory::Combat)
+                                .name_of(*target, &names, &renderables)
+                                .append(" shrugs off the ")
+                                .colour(RGB::named(SPELL_COLOUR))
+                                .append(&spell.name)
+                                .colour(RGB::named(rltk::WHITE))
+                                .append("."),
+                        );
+                    } else {
+                        SufferDamage::new_damage(&mut suffer_damage, *target, damage, caster_name);
+                        hits.push(
+                            LogEntry::new(LogCategory::Combat)
+                                .colour(RGB::named(SPELL_COLOUR))
+                                .append(&spell.name)
+                                .colour(RGB::named(rltk::WHITE))
+                                .append(" hits ")
+                                .name_of(*target, &names, &renderables)
+                                .damage(damage),
+                        );
+                    }
+                }
+                if let Some(heal) = spell.healing {
+                    let before = stats.curr_hp;
+                    stats.curr_hp = i32::min(stats.max_hp, stats.curr_hp + heal);
+                    effects.push(format!("healing {} hp", stats.curr_hp - before));
+                }
+                if let Some(turns) = spell.slows {
+                    // Being slowed again starts over, rather than adding up
+                    slowed
+                        .insert(*target, Slowed { turns })
+                        .expect("Unable to insert slow");
+                    hits.push(
+                        LogEntry::new(LogCategory::Combat)
+                            .name_of(*target, &names, &renderables)
+                            .append(" is slowed."),
+                    );
+                }
+            }
+
+            if entity == *player_entity {
+                gamelog.add(
+                    LogEntry::new(LogCategory::Combat)
+                        .append("You cast ")
+                        .colour(RGB::named(SPELL_COLOUR))
+                        .append(&spell.name)
+                        .colour(RGB::named(rltk::WHITE))
+                        .used_on(
+                            entity,
+                            targets.first().copied(),
+                            &effects,
+                            &names,
+                            &renderables,
+                        ),
+                );
+            }
+            for hit in hits {
+                gamelog.add(hit);
+            }
+        }
+
+        wants_cast.clear();
+    }
+}
@@ -0,0 +1,347 @@
+//! The wizard console: debug commands for trying things out without playing until they happen.
+//! Only built with `--features wizard`, and opened in the game with the ` key.
+
+use super::{
+    camera::{VIEW_HEIGHT, VIEW_WIDTH},
+    delete_the_dead,
+    gamelog::{GameLog, LogCategory, LogEntry},
+    raws::{self, RawMaster},
+    CombatStats, Invulnerable, KilledBy, Map, MapIndexingSystem, Monster, Name, Position, State,
+    Viewshed, VisibilitySystem,
+};
+use rltk::{Point, Rltk, VirtualKeyCode, RGB};
+use specs::prelude::*;
+
+const HELP: &str = "Commands: reveal, teleport <x> <y>, spawn <name>, hp <n>, kill, god, regen";
+
+/// The command being typed into the console. Stored as a resource in the World.
+#[derive(Default)]
+pub struct WizardConsole {
+    pub input: String,
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum WizardCommand {
+    /// Marks the whole map as seen.
+    Reveal,
+    Teleport {
+        x: i32,
+        y: i32,
+    },
+    /// Spawns a monster or item next to the player.
+    Spawn {
+        name: String,
+    },
+    /// Sets the player's HP, raising their maximum to match if needed.
+    SetHp {
+        hp: i32,
+    },
+    /// Kills every monster the player can see.
+    KillVisible,
+    /// Makes the player take no damage, or takes that away again.
+    GodMode,
+    /// Replaces the level with a new one at the same depth.
+    Regenerate,
+    Help,
+}
+
+fn parse_number(word: &str) -> Result<i32, String> {
+    word.parse::<i32>()
+        .map_err(|_| format!("'{}' is not a number.", word))
+}
+
+pub fn parse_command(line: &str) -> Result<WizardCommand, String> {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        ["reveal"] => Ok(WizardCommand::Reveal),
+        ["teleport", x, y] => Ok(WizardCommand::Teleport {
+            x: parse_number(x)?,
+            y: parse_number(y)?,
+        }),
+        ["spawn", name @ ..] if !name.is_empty() => Ok(WizardCommand::Spawn {
+            name: name.join(" "),
+        }),
+        ["hp", hp] => Ok(WizardCommand::SetHp {
+            hp: parse_number(hp)?,
+        }),
+        ["kill"] => Ok(WizardCommand::KillVisible),
+        ["god"] => Ok(WizardCommand::GodMode),
+        ["regen"] => Ok(WizardCommand::Regenerate),
+        ["help"] => Ok(WizardCommand::Help),
+        _ => Err(format!("Unknown command '{}'. {}", line.trim(), HELP)),
+    }
+}
+
+impl State {
+    /// Runs a wizard console command, such as `teleport 10 20`. What happened, or what went
+    /// wrong, is also written to the log. Takes no time.
+    pub fn wizard(&mut self, line: &str) -> Result<(), String> {
+        let result = parse_command(line).and_then(|command| self.run_wizard_command(command));
+
+        let message = match &result {
+            Ok(message) | Err(message) => message.clone(),
+        };
+        self.ecs.write_resource::<GameLog>().add(
+            LogEntry::new(LogCategory::System)
+                .colour(RGB::named(rltk::MAGENTA))
+                .append(message),
+        );
+
+        // Show what changed straight away, rather than after the next turn
+        let mut vis = VisibilitySystem {};
+        vis.run_now(&self.ecs);
+        let mut mapindex = MapIndexingSystem {};
+        mapindex.run_now(&self.ecs);
+        self.ecs.maintain();
+
+        result.map(|_| ())
+    }
+
+    /// Carries out a command, returning what to tell the player about it.
+    fn run_wizard_command(&mut self, command: WizardCommand) -> Result<String, String> {
+        let player_entity = *self.ecs.fetch::<Entity>();
+
+        match command {
+            WizardCommand::Reveal => {
+                let mut map = self.ecs.fetch_mut::<Map>();
+                for revealed in map.revealed_tiles.iter_mut() {
+                    *revealed = true;
+                }
+                Ok("The whole level is revealed.".to_string())
+            }
+            WizardCommand::Teleport { x, y } => {
+                let map = self.ecs.fetch::<Map>();
+                if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                    return Err(format!("{}, {} is off the map.", x, y));
+                }
+                if map.blocked[map.xy_idx(x, y)] {
+                    return Err(format!("{}, {} is blocked.", x, y));
+                }
+
+                let mut positions = self.ecs.write_storage::<Position>();
+                if let Some(pos) = positions.get_mut(player_entity) {
+                    pos.x = x;
+                    pos.y = y;
+                }
+                *self.ecs.write_resource::<Point>() = Point::new(x, y);
+                let mut viewsheds = self.ecs.write_storage::<Viewshed>();
+                if let Some(vs) = viewsheds.get_mut(player_entity) {
+                    vs.dirty = true;
+                }
+                Ok(format!("You teleport to {}, {}.", x, y))
+            }
+            WizardCommand::Spawn { name } => {
+                let key = {
+                    let raws = self.ecs.fetch::<RawMaster>();
+                    let key = raws
+                        .names()
+                        .find(|mob| mob.eq_ignore_ascii_case(&name))
+                        .map(|mob| mob.to_string());
+                    key.ok_or_else(|| {
+                        let names: Vec<&str> = raws.names().collect();
+                        format!(
+                            "There is nothing called '{}'. Try one of: {}",
+                            name,
+                            names.join(", ")
+                        )
+                    })?
+                };
+
+                let position = self
+                    .free_tile_next_to_player()
+                    .ok_or_else(|| "There is no room next to you to spawn anything.".to_string())?;
+                let spawned = raws::spawn_named_entity(&mut self.ecs, &key, position)
+                    .expect("Names come from the raws");
+                let names = self.ecs.read_storage::<Name>();
+                Ok(format!("{} appears.", names.get(spawned).unwrap().name))
+            }
+            WizardCommand::SetHp { hp } => {
+                if hp < 1 {
+                    return Err("HP must be at least 1.".to_string());
+                }
+                let mut combat_stats = self.ecs.write_storage::<CombatStats>();
+                if let Some(stats) = combat_stats.get_mut(player_entity) {
+                    stats.curr_hp = hp;
+                    stats.max_hp = i32::max(stats.max_hp, hp);
+                }
+                Ok(format!("Your HP is now {}.", hp))
+            }
+            WizardCommand::KillVisible => {
+                let mut killed = 0;
+                {
+                    let entities = self.ecs.entities();
+                    let viewsheds = self.ecs.read_storage::<Viewshed>();
+                    let monsters = self.ecs.read_storage::<Monster>();
+                    let positions = self.ecs.read_storage::<Position>();
+                    let mut combat_stats = self.ecs.write_storage::<CombatStats>();
+                    let mut killed_by = self.ecs.write_storage::<KilledBy>();
+
+                    let visible = &viewsheds
+                        .get(player_entity)
+                        .expect("The player has no viewshed")
+                        .visible_tiles;
+                    for (entity, _monster, pos, stats) in
+                        (&entities, &monsters, &positions, &mut combat_stats).join()
+                    {
+                        if visible.contains(&Point::new(pos.x, pos.y)) {
+                            stats.curr_hp = 0;
+                            killed_by
+                                .insert(
+                                    entity,
+                                    KilledBy {
+                                        name: "a wizard".to_string(),
+                                    },
+                                )
+                                .expect("Unable to insert cause of death");
+                            killed += 1;
+                        }
+                    }
+                }
+                delete_the_dead(&mut self.ecs);
+                Ok(format!("Killed {} monsters.", killed))
+            }
+            WizardCommand::GodMode => {
+                let mut invulnerable = self.ecs.write_storage::<Invulnerable>();
+                if invulnerable.remove(player_entity).is_some() {
+                    Ok("God mode is off.".to_string())
+                } else {
+                    invulnerable
+                        .insert(player_entity, Invulnerable {})
+                        .expect("Unable to insert god mode");
+                    Ok("God mode is on.".to_string())
+                }
+            }
+            WizardCommand::Regenerate => {
+                let depth = self.ecs.fetch::<Map>().depth;
+                self.change_level(depth);
+                Ok(format!("Depth {} is generated anew.", depth))
+            }
+            WizardCommand::Help => Ok(HELP.to_string()),
+        }
+    }
+
+    /// The first tile around the player that nothing is standing on.
+    fn free_tile_next_to_player(&self) -> Option<Position> {
+        let player_pos = *self.ecs.fetch::<Point>();
+        let map = self.ecs.fetch::<Map>();
+        for y in player_pos.y - 1..=player_pos.y + 1 {
+            for x in player_pos.x - 1..=player_pos.x + 1 {
+                if x < 0 || x >= map.width || y < 0 || y >= map.height {
+                    continue;
+                }
+                let idx = map.xy_idx(x, y);
+                if !map.blocked[idx] && map.tile_content[idx].is_empty() {
+                    return Some(Position { x, y });
+                }
+            }
+        }
+        None
+    }
+}
+
+pub enum ConsoleResult {
+    Typing,
+    Cancel,
+    Run { line: String },
+}
+
+/// Reads this frame's key into the console. Enter runs the command, and Escape (or ` again)
+/// closes the console.
+pub fn console_input(ecs: &World, ctx: &mut Rltk) -> ConsoleResult {
+    let mut console = ecs.fetch_mut::<WizardConsole>();
+    match ctx.key {
+        None => ConsoleResult::Typing,
+        Some(VirtualKeyCode::Escape) | Some(VirtualKeyCode::Grave) => {
+            console.input.clear();
+            ConsoleResult::Cancel
+        }
+        Some(VirtualKeyCode::Return) | Some(VirtualKeyCode::NumpadEnter) => {
+            let line = std::mem::take(&mut console.input);
+            if line.trim().is_empty() {
+                ConsoleResult::Cancel
+            } else {
+                ConsoleResult::Run { line }
+            }
+        }
+        Some(VirtualKeyCode::Back) => {
+            console.input.pop();
+            ConsoleResult::Typing
+        }
+        Some(key) => {
+            if let Some(c) = key_to_char(key, ctx.shift) {
+                console.input.push(c);
+            }
+            ConsoleResult::Typing
+        }
+    }
+}
+
+/// Draws the command being typed across the bottom row of the map.
+pub fn draw_console(ecs: &World, ctx: &mut Rltk) {
+    let console = ecs.fetch::<WizardConsole>();
+    let y = VIEW_HEIGHT - 1;
+    for x in 0..VIEW_WIDTH {
+        ctx.set(
+            x,
+            y,
+            RGB::named(rltk::MAGENTA),
+            RGB::named(rltk::BLACK),
+            rltk::to_cp437(' '),
+        );
+    }
+    let prompt = format!("> {}_", console.input);
+    ctx.print_color(
+        0,
+        y,
+        RGB::named(rltk::MAGENTA),
+        RGB::named(rltk::BLACK),
+        &prompt,
+    );
+}
+
+/// The character a key types, for the few the console's commands need.
+fn key_to_char(key: VirtualKeyCode, shift: bool) -> Option<char> {
+    use VirtualKeyCode::*;
+    let c = match key {
+        A => 'a',
+        B => 'b',
+        C => 'c',
+        D => 'd',
+        E => 'e',
+        F => 'f',
+        G => 'g',
+        H => 'h',
+        I => 'i',
+        J => 'j',
+        K => 'k',
+        L => 'l',
+        M => 'm',
+        N => 'n',
+        O => 'o',
+        P => 'p',
+        Q => 'q',
+        R => 'r',
+        S => 's',
+        T => 't',
+        U => 'u',
+        V => 'v',
+        W => 'w',
+        X => 'x',
+        Y => 'y',
+        Z => 'z',
+        Key0 | Numpad0 => '0',
+        Key1 | Numpad1 => '1',
+        Key2 | Numpad2 => '2',
+        Key3 | Numpad3 => '3',
+        Key4 | Numpad4 => '4',
+        Key5 | Numpad5 => '5',
+        Key6 | Numpad6 => '6',
+        Key7 | Numpad7 => '7',
+        Key8 | Numpad8 => '8',
+        Key9 | Numpad9 => '9',
+        Space => ' ',
+        Minus | NumpadSubtract => '-',
+        _ => return None,
+    };
+    Some(if shift { c.to_ascii_uppercase() } else { c })
+}
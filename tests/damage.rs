@@ -0,0 +1,74 @@
+mod support;
+
+use dworld_rust::*;
+use specs::prelude::*;
+use support::*;
+
+fn hurt(ecs: &mut World, victim: Entity, amount: i32, source: &str) {
+    let mut damage = ecs.write_storage::<SufferDamage>();
+    SufferDamage::new_damage(&mut damage, victim, amount, source);
+}
+
+#[test]
+fn damage_adds_up_and_is_cleared() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let monster = world.monsters[0];
+
+    hurt(&mut world.ecs, monster, 3, "Player");
+    hurt(&mut world.ecs, monster, 2, "Player");
+    DamageSystem {}.run_now(&world.ecs);
+
+    assert_eq!(hp(&world.ecs, monster), 5);
+    assert!(world.ecs.read_storage::<SufferDamage>().is_empty());
+    assert!(world.ecs.read_storage::<KilledBy>().get(monster).is_none());
+}
+
+#[test]
+fn dead_monsters_are_removed() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let monster = world.monsters[0];
+
+    hurt(&mut world.ecs, monster, 10, "Player");
+    DamageSystem {}.run_now(&world.ecs);
+    delete_the_dead(&mut world.ecs);
+    world.ecs.maintain();
+
+    assert!(!world.ecs.is_alive(monster));
+    assert!(log_entries(&world.ecs).last().unwrap().ends_with("is dead"));
+}
+
+#[test]
+fn the_player_dying_ends_the_game() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let player = world.player;
+
+    hurt(&mut world.ecs, player, 20, "Monster");
+    hurt(&mut world.ecs, player, 20, "Another Monster");
+    DamageSystem {}.run_now(&world.ecs);
+    delete_the_dead(&mut world.ecs);
+    world.ecs.maintain();
+
+    // The player stays around for the game over screen
+    assert!(world.ecs.is_alive(player));
+    assert!(*world.ecs.fetch::<RunState>() == RunState::GameOver);
+    let killed_by = world.ecs.read_storage::<KilledBy>();
+    assert_eq!(killed_by.get(player).unwrap().name, "Another Monster");
+}
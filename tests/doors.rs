@@ -0,0 +1,47 @@
+mod support;
+
+use dworld_rust::*;
+use support::*;
+
+fn door_tile(world: &TestWorld) -> TileType {
+    let map = world.ecs.fetch::<Map>();
+    map.tiles[map.xy_idx(2, 1)]
+}
+
+#[test]
+fn items_lying_in_a_doorway_dont_stop_it_closing() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@'.#
+        #####
+        ",
+    );
+    spawn_item(&mut world.ecs, 2, 1);
+    run_systems_as(&mut world.ecs, RunState::PreRun);
+
+    play_turn(&mut world.ecs, PlayerCommand::CloseDoors);
+
+    assert!(door_tile(&world) == TileType::ClosedDoor);
+    assert_eq!(
+        log_entries(&world.ecs).last().unwrap(),
+        "You close the door."
+    );
+}
+
+#[test]
+fn doors_cant_be_closed_on_someone() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@'.#
+        #####
+        ",
+    );
+    spawn_monster(&mut world.ecs, 2, 1);
+    run_systems_as(&mut world.ecs, RunState::PreRun);
+
+    play_turn(&mut world.ecs, PlayerCommand::CloseDoors);
+
+    assert!(door_tile(&world) == TileType::OpenDoor);
+}
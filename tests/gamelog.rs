@@ -0,0 +1,183 @@
+mod support;
+
+use dworld_rust::*;
+use rltk::RGB;
+use support::*;
+
+fn line_text(line: &[LogSpan]) -> String {
+    line.iter().map(|span| span.text.as_str()).collect()
+}
+
+#[test]
+fn repeated_entries_are_counted() {
+    let mut log = GameLog::new();
+    log.log(LogCategory::System, "There is no way down from here.");
+    log.log(LogCategory::System, "There is no way down from here.");
+    log.log(LogCategory::System, "There is no way down from here.");
+    log.log(LogCategory::Combat, "There is no way down from here.");
+
+    let entries: Vec<String> = log.entries().map(|entry| entry.text()).collect();
+    assert_eq!(
+        entries,
+        vec![
+            "There is no way down from here. x3",
+            "There is no way down from here.",
+        ]
+    );
+}
+
+#[test]
+fn old_entries_are_forgotten() {
+    let mut log = GameLog::new();
+    for i in 0..MAX_LOG_ENTRIES + 10 {
+        log.log(LogCategory::System, i);
+    }
+
+    assert_eq!(log.entries().len(), MAX_LOG_ENTRIES);
+    assert_eq!(log.entries().next().unwrap().text(), "10");
+}
+
+#[test]
+fn long_entries_wrap_between_words() {
+    let entry = LogEntry::new(LogCategory::Combat)
+        .colour(RGB::named(rltk::RED))
+        .append("Goblin #12")
+        .colour(RGB::named(rltk::WHITE))
+        .append(" hits Player, for 4 hp.");
+
+    let lines = entry.wrap(16);
+
+    let text: Vec<String> = lines.iter().map(|line| line_text(line)).collect();
+    assert_eq!(text, vec!["Goblin #12 hits", "Player, for 4", "hp."]);
+    assert!(text.iter().all(|line| line.chars().count() <= 16));
+    // Colours carry over into the wrapped lines
+    assert_eq!(lines[0][0].colour, RGB::named(rltk::RED));
+    assert_eq!(lines[0][1].colour, RGB::named(rltk::WHITE));
+}
+
+#[test]
+fn words_longer_than_a_line_are_cut_up() {
+    let entry = LogEntry::new(LogCategory::System).append("Aaaaaaaaaaaaaaaa!");
+
+    let text: Vec<String> = entry.wrap(6).iter().map(|line| line_text(line)).collect();
+
+    assert_eq!(text, vec!["Aaaaaa", "aaaaaa", "aaaa!"]);
+}
+
+#[test]
+fn entries_are_stamped_with_the_turn() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@.m#
+        #####
+        ",
+    );
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+
+    let log = world.ecs.fetch::<GameLog>();
+    let attack = log
+        .entries()
+        .find(|entry| entry.text().starts_with("Player hits"))
+        .unwrap();
+    assert_eq!(attack.category, LogCategory::Combat);
+    assert_eq!(attack.turn, 2);
+}
+
+#[test]
+fn reading_the_log_takes_no_time() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@.m#
+        #####
+        ",
+    );
+    let player = world.player;
+    let before = energy(&world.ecs, player).energy;
+
+    let newrunstate = handle_command(&mut world.ecs, PlayerCommand::ShowLog);
+
+    assert!(
+        newrunstate
+            == RunState::MessageLog {
+                scroll: 0,
+                filter: None
+            }
+    );
+    assert_eq!(energy(&world.ecs, player).energy, before);
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 0);
+}
+
+#[test]
+fn the_history_can_show_one_category() {
+    let mut log = GameLog::new();
+    log.log(LogCategory::Combat, "You hit the orc.");
+    log.log(LogCategory::Loot, "You pick up a potion.");
+    log.log(LogCategory::Combat, "The orc is dead.");
+
+    let text = |filter| -> Vec<String> {
+        log.history(filter, 40)
+            .iter()
+            .map(|(_, line)| line_text(line))
+            .collect()
+    };
+    assert_eq!(
+        text(Some(LogCategory::Combat)),
+        vec!["You hit the orc.", "The orc is dead."]
+    );
+    assert_eq!(text(Some(LogCategory::Loot)), vec!["You pick up a potion."]);
+    assert!(text(Some(LogCategory::System)).is_empty());
+    assert_eq!(text(None).len(), 3);
+}
+
+#[test]
+fn paging_through_the_history_stops_at_either_end() {
+    // 100 lines, 40 to a page: the oldest line is at the top once 60 are hidden below
+    let page = |action, scroll| scroll_history(action, scroll, None, 100, 40).0;
+    assert_eq!(page(HistoryAction::OlderPage, 0), 40);
+    assert_eq!(page(HistoryAction::OlderPage, 40), 60);
+    assert_eq!(page(HistoryAction::Older, 60), 60);
+    assert_eq!(page(HistoryAction::NewerPage, 60), 20);
+    assert_eq!(page(HistoryAction::NewerPage, 20), 0);
+    assert_eq!(page(HistoryAction::Newer, 0), 0);
+    assert_eq!(page(HistoryAction::Oldest, 0), 60);
+
+    // A history shorter than a page can't scroll at all
+    assert_eq!(
+        scroll_history(HistoryAction::OlderPage, 0, None, 10, 40),
+        (0, None)
+    );
+}
+
+#[test]
+fn changing_the_filter_shows_its_newest_messages() {
+    assert_eq!(
+        scroll_history(HistoryAction::NextFilter, 25, None, 100, 40),
+        (0, Some(LogCategory::Combat))
+    );
+    assert_eq!(
+        scroll_history(
+            HistoryAction::NextFilter,
+            0,
+            Some(LogCategory::Loot),
+            100,
+            40
+        ),
+        (0, None)
+    );
+}
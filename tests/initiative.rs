@@ -0,0 +1,133 @@
+mod support;
+
+use dworld_rust::*;
+use specs::prelude::*;
+use support::*;
+
+fn set_speed(ecs: &mut World, entity: Entity, speed: i32) {
+    ecs.write_storage::<Energy>().get_mut(entity).unwrap().speed = speed;
+}
+
+#[test]
+fn the_player_moves_first() {
+    let world = world_from_ascii(
+        "
+        #####
+        #@.m#
+        #####
+        ",
+    );
+
+    assert!(*world.ecs.fetch::<RunState>() == RunState::AwaitingInput);
+    let turns = world.ecs.read_storage::<MyTurn>();
+    assert!(turns.get(world.player).is_some());
+    assert!(turns.get(world.monsters[0]).is_none());
+}
+
+#[test]
+fn faster_monsters_act_more_often() {
+    let mut world = world_from_ascii(
+        "
+        ##############################
+        #@...........................#
+        #............................#
+        #............................#
+        #............................#
+        #m...........................#
+        #m...........................#
+        #m...........................#
+        ##############################
+        ",
+    );
+    let (bat, normal, zombie) = (world.monsters[0], world.monsters[1], world.monsters[2]);
+    set_speed(&mut world.ecs, bat, 2 * NORMAL_SPEED);
+    set_speed(&mut world.ecs, zombie, NORMAL_SPEED / 2);
+
+    // Nobody can see anyone else across the room, so everyone just waits
+    for viewshed in (&mut world.ecs.write_storage::<Viewshed>()).join() {
+        viewshed.range = 0;
+        viewshed.dirty = true;
+    }
+    let before: Vec<i32> = [bat, normal, zombie]
+        .iter()
+        .map(|entity| energy(&world.ecs, *entity).energy)
+        .collect();
+
+    // The player steps back and forth for ten turns
+    for turn in 0..10 {
+        let delta_x = if turn % 2 == 0 { 1 } else { -1 };
+        play_turn(
+            &mut world.ecs,
+            PlayerCommand::Move {
+                delta_x,
+                delta_y: 0,
+            },
+        );
+    }
+
+    // Energy gained, less what is left over, tells how many turns were taken
+    let ticks = ACTION_COST * 10 / NORMAL_SPEED;
+    let turns_taken = |entity: Entity, before: i32| {
+        let energy = energy(&world.ecs, entity);
+        (before + energy.speed * ticks - energy.energy) / ACTION_COST
+    };
+    // The bat may be halfway to its next turn when the player's comes around
+    assert!((19..=20).contains(&turns_taken(bat, before[0])));
+    assert_eq!(turns_taken(normal, before[1]), 10);
+    assert_eq!(turns_taken(zombie, before[2]), 5);
+}
+
+#[test]
+fn bumping_into_a_wall_takes_no_time() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@.m#
+        #####
+        ",
+    );
+
+    let runstate = handle_command(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 0,
+            delta_y: -1,
+        },
+    );
+
+    assert!(runstate == RunState::AwaitingInput);
+    assert!(energy(&world.ecs, world.player).energy >= ACTION_COST);
+}
+
+#[test]
+fn acting_ends_the_players_turn() {
+    let mut world = world_from_ascii(
+        "
+        ######
+        #@...#
+        #...m#
+        ######
+        ",
+    );
+    let player = world.player;
+    let energy_before = energy(&world.ecs, player).energy;
+
+    let runstate = handle_command(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+
+    assert!(runstate == RunState::Ticking);
+    assert_eq!(
+        energy(&world.ecs, player).energy,
+        energy_before - ACTION_COST
+    );
+    assert!(world.ecs.read_storage::<MyTurn>().get(player).is_none());
+
+    // Time passes until the player can act again
+    assert!(run_ticks(&mut world.ecs) == RunState::AwaitingInput);
+    assert!(world.ecs.read_storage::<MyTurn>().get(player).is_some());
+}
@@ -0,0 +1,134 @@
+mod support;
+
+use dworld_rust::*;
+use specs::prelude::*;
+use support::*;
+
+#[test]
+fn picking_up_moves_the_item_into_the_backpack() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@!.#
+        #####
+        ",
+    );
+    let player = world.player;
+    let potion = world.items[0];
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+    play_turn(&mut world.ecs, PlayerCommand::PickUp);
+
+    assert_eq!(backpack(&world.ecs, player), vec![potion]);
+    // Carried items are nowhere on the map
+    assert!(world.ecs.read_storage::<Position>().get(potion).is_none());
+    assert!(log_entries(&world.ecs).contains(&"You pick up the Potion.".to_string()));
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 2);
+}
+
+#[test]
+fn there_is_nothing_to_pick_up_on_an_empty_floor() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@.!#
+        #####
+        ",
+    );
+    let player = world.player;
+
+    let newrunstate = handle_command(&mut world.ecs, PlayerCommand::PickUp);
+
+    // Nothing happened, so it is still the player's turn
+    assert!(newrunstate == RunState::AwaitingInput);
+    assert!(backpack(&world.ecs, player).is_empty());
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 0);
+    assert_eq!(
+        log_entries(&world.ecs).last().unwrap(),
+        "There is nothing here to pick up."
+    );
+}
+
+#[test]
+fn items_do_not_block_the_way() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@!.#
+        #####
+        ",
+    );
+    let player = world.player;
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+
+    assert_eq!(position(&world.ecs, player), (2, 1));
+}
+
+#[test]
+fn dropping_puts_the_item_where_the_player_stands() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@!.#
+        #####
+        ",
+    );
+    let player = world.player;
+    let potion = world.items[0];
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+    play_turn(&mut world.ecs, PlayerCommand::PickUp);
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+
+    play_turn(&mut world.ecs, PlayerCommand::DropItem { item: potion });
+
+    assert!(backpack(&world.ecs, player).is_empty());
+    assert_eq!(position(&world.ecs, potion), (3, 1));
+    assert_eq!(
+        log_entries(&world.ecs).last().unwrap(),
+        "You drop the Potion."
+    );
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 4);
+}
+
+#[test]
+fn only_carried_items_can_be_dropped() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@!.#
+        #####
+        ",
+    );
+    let potion = world.items[0];
+
+    let newrunstate = handle_command(&mut world.ecs, PlayerCommand::DropItem { item: potion });
+
+    assert!(newrunstate == RunState::AwaitingInput);
+    assert_eq!(position(&world.ecs, potion), (2, 1));
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 0);
+}
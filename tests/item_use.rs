@@ -0,0 +1,137 @@
+mod support;
+
+use dworld_rust::*;
+use specs::prelude::*;
+use support::*;
+
+fn set_stats(world: &mut TestWorld, curr_hp: i32, curr_mana: i32) {
+    let mut combat_stats = world.ecs.write_storage::<CombatStats>();
+    let stats = combat_stats.get_mut(world.player).unwrap();
+    stats.curr_hp = curr_hp;
+    stats.curr_mana = curr_mana;
+}
+
+fn add_effect<T: Component>(world: &mut TestWorld, item: Entity, effect: T) {
+    world
+        .ecs
+        .write_storage::<T>()
+        .insert(item, effect)
+        .expect("Unable to insert effect");
+}
+
+#[test]
+fn healing_stops_at_the_maximum() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@..#
+        #####
+        ",
+    );
+    set_stats(&mut world, 25, 50);
+    let potion = give_item(&mut world.ecs, world.player);
+    add_effect(&mut world, potion, ProvidesHealing { heal_amount: 8 });
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: potion,
+            target: None,
+        },
+    );
+
+    assert_eq!(stats(&world.ecs, world.player).curr_hp, 30);
+    assert_eq!(
+        log_entries(&world.ecs).last().unwrap(),
+        "You use the Potion, healing 5 hp."
+    );
+}
+
+#[test]
+fn mana_stops_at_the_maximum() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@..#
+        #####
+        ",
+    );
+    set_stats(&mut world, 30, 10);
+    let potion = give_item(&mut world.ecs, world.player);
+    add_effect(&mut world, potion, RestoresMana { mana_amount: 15 });
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: potion,
+            target: None,
+        },
+    );
+    assert_eq!(stats(&world.ecs, world.player).curr_mana, 25);
+
+    add_effect(&mut world, potion, RestoresMana { mana_amount: 40 });
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: potion,
+            target: None,
+        },
+    );
+    assert_eq!(stats(&world.ecs, world.player).curr_mana, 50);
+}
+
+#[test]
+fn effects_combine_and_consumables_are_used_up() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@..#
+        #####
+        ",
+    );
+    set_stats(&mut world, 20, 50);
+    let potion = give_item(&mut world.ecs, world.player);
+    add_effect(&mut world, potion, IncreasesMaxHp { amount: 5 });
+    add_effect(&mut world, potion, ProvidesHealing { heal_amount: 100 });
+    add_effect(&mut world, potion, Consumable {});
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: potion,
+            target: None,
+        },
+    );
+
+    let stats = stats(&world.ecs, world.player);
+    assert_eq!((stats.curr_hp, stats.max_hp), (35, 35));
+    assert!(!world.ecs.is_alive(potion));
+    assert!(backpack(&world.ecs, world.player).is_empty());
+}
+
+#[test]
+fn items_without_effects_cannot_be_used() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@..#
+        #####
+        ",
+    );
+    let rock = give_item(&mut world.ecs, world.player);
+
+    let newrunstate = handle_command(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: rock,
+            target: None,
+        },
+    );
+
+    assert!(newrunstate == RunState::AwaitingInput);
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 0);
+    assert_eq!(
+        log_entries(&world.ecs).last().unwrap(),
+        "You can't use the Potion."
+    );
+}
@@ -0,0 +1,65 @@
+mod support;
+
+use dworld_rust::*;
+use specs::prelude::*;
+use support::*;
+
+#[test]
+fn entities_are_indexed_by_tile() {
+    let world = world_from_ascii(
+        "
+        ######
+        #@.m.#
+        ######
+        ",
+    );
+    let map = world.ecs.fetch::<Map>();
+
+    assert_eq!(map.tile_content[map.xy_idx(1, 1)], vec![world.player]);
+    assert_eq!(map.tile_content[map.xy_idx(3, 1)], vec![world.monsters[0]]);
+    assert!(map.tile_content[map.xy_idx(2, 1)].is_empty());
+}
+
+#[test]
+fn blocking_entities_block_their_tile() {
+    let world = world_from_ascii(
+        "
+        ######
+        #@.m+#
+        ######
+        ",
+    );
+    let map = world.ecs.fetch::<Map>();
+
+    // The player has no BlocksTile, so can be walked through by nothing but their own moves
+    assert!(!map.blocked[map.xy_idx(1, 1)]);
+    assert!(!map.blocked[map.xy_idx(2, 1)]);
+    assert!(map.blocked[map.xy_idx(3, 1)]);
+    assert!(map.blocked[map.xy_idx(4, 1)]);
+    assert!(map.blocked[map.xy_idx(0, 1)]);
+}
+
+#[test]
+fn the_index_follows_entities_around() {
+    let world = world_from_ascii(
+        "
+        ######
+        #@.m.#
+        ######
+        ",
+    );
+    let monster = world.monsters[0];
+    world
+        .ecs
+        .write_storage::<Position>()
+        .insert(monster, Position { x: 4, y: 1 })
+        .unwrap();
+
+    MapIndexingSystem {}.run_now(&world.ecs);
+
+    let map = world.ecs.fetch::<Map>();
+    assert!(!map.blocked[map.xy_idx(3, 1)]);
+    assert!(map.tile_content[map.xy_idx(3, 1)].is_empty());
+    assert!(map.blocked[map.xy_idx(4, 1)]);
+    assert_eq!(map.tile_content[map.xy_idx(4, 1)], vec![monster]);
+}
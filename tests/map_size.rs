@@ -0,0 +1,35 @@
+use dworld_rust::*;
+
+#[test]
+fn builders_need_room_for_their_levels() {
+    assert!(check_map_size(Some("fortress"), 80, 43).is_ok());
+    assert_eq!(
+        check_map_size(Some("fortress"), 40, 20),
+        Err("The 'fortress' builder needs a map of at least 66x23, not 40x20".to_string())
+    );
+    assert!(check_map_size(Some("rooms"), 40, 20).is_ok());
+    // Random levels only pick builders that fit
+    assert!(check_map_size(None, 40, 20).is_ok());
+    assert!(check_map_size(None, 19, 40).is_err());
+}
+
+#[test]
+fn maps_have_a_largest_size() {
+    assert!(check_map_size(None, 200, 120).is_ok());
+    assert!(check_map_size(Some("rooms"), 250, 250).is_err());
+}
+
+#[test]
+fn random_levels_are_built_on_maps_too_small_for_the_fortress() {
+    for seed in 0..10 {
+        let mut gs = State::new(
+            MapGenSettings {
+                builder_name: None,
+                width: 40,
+                height: 20,
+            },
+            Some(seed),
+        );
+        gs.new_game();
+    }
+}
@@ -0,0 +1,99 @@
+mod support;
+
+use dworld_rust::*;
+use specs::prelude::*;
+use support::*;
+
+#[test]
+fn attacking_a_monster_damages_it() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let monster = world.monsters[0];
+
+    handle_command(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+    MeleeCombatSystem {}.run_now(&world.ecs);
+
+    // Power 5 against defense 1, give or take one
+    let damage = world.ecs.read_storage::<SufferDamage>();
+    let dealt: i32 = damage.get(monster).unwrap().amount.iter().sum();
+    assert!((3..=5).contains(&dealt), "dealt {}", dealt);
+    assert_eq!(damage.get(monster).unwrap().last_hit_by, "Player");
+    assert!(world.ecs.read_storage::<WantsToMelee>().is_empty());
+    assert!(log_entries(&world.ecs)[0].starts_with("Player hits Monster"));
+}
+
+#[test]
+fn armour_can_stop_every_blow() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let monster = world.monsters[0];
+    world
+        .ecs
+        .write_storage::<CombatStats>()
+        .get_mut(monster)
+        .unwrap()
+        .defense = 100;
+
+    handle_command(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 1,
+            delta_y: 0,
+        },
+    );
+    MeleeCombatSystem {}.run_now(&world.ecs);
+
+    assert!(world
+        .ecs
+        .read_storage::<SufferDamage>()
+        .get(monster)
+        .is_none());
+    assert!(log_entries(&world.ecs)[0].contains("is unable to hurt"));
+}
+
+#[test]
+fn the_dead_do_not_fight_back() {
+    let world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let (player, monster) = (world.player, world.monsters[0]);
+    world
+        .ecs
+        .write_storage::<CombatStats>()
+        .get_mut(monster)
+        .unwrap()
+        .curr_hp = 0;
+    world
+        .ecs
+        .write_storage::<WantsToMelee>()
+        .insert(monster, WantsToMelee { target: player })
+        .unwrap();
+
+    MeleeCombatSystem {}.run_now(&world.ecs);
+
+    assert!(world
+        .ecs
+        .read_storage::<SufferDamage>()
+        .get(player)
+        .is_none());
+}
@@ -0,0 +1,126 @@
+mod support;
+
+use dworld_rust::*;
+use rltk::Point;
+use specs::prelude::*;
+use support::*;
+
+#[test]
+fn monsters_only_act_on_their_turn() {
+    let mut world = world_from_ascii(
+        "
+        #######
+        #@...m#
+        #######
+        ",
+    );
+    let monster = world.monsters[0];
+
+    run_systems_as(&mut world.ecs, RunState::AwaitingInput);
+    assert_eq!(position(&world.ecs, monster), (5, 1));
+
+    give_turn(&mut world.ecs, monster);
+    run_systems_as(&mut world.ecs, RunState::AwaitingInput);
+    assert_eq!(position(&world.ecs, monster), (4, 1));
+    assert!(world.ecs.read_storage::<MyTurn>().get(monster).is_none());
+}
+
+#[test]
+fn monsters_chase_a_player_they_can_see() {
+    let mut world = world_from_ascii(
+        "
+        #######
+        #@...m#
+        #######
+        ",
+    );
+    let monster = world.monsters[0];
+
+    give_turn(&mut world.ecs, monster);
+    run_systems_as(&mut world.ecs, RunState::AwaitingInput);
+    assert_eq!(position(&world.ecs, monster), (4, 1));
+    give_turn(&mut world.ecs, monster);
+    run_systems_as(&mut world.ecs, RunState::AwaitingInput);
+    assert_eq!(position(&world.ecs, monster), (3, 1));
+}
+
+#[test]
+fn monsters_ignore_a_player_they_cannot_see() {
+    let mut world = world_from_ascii(
+        "
+        #######
+        #@.#.m#
+        #..#..#
+        #.....#
+        #######
+        ",
+    );
+    let monster = world.monsters[0];
+    let energy_before = energy(&world.ecs, monster).energy;
+
+    give_turn(&mut world.ecs, monster);
+    run_systems_as(&mut world.ecs, RunState::AwaitingInput);
+
+    // Waiting still uses up the monster's turn
+    assert_eq!(position(&world.ecs, monster), (5, 1));
+    assert_eq!(
+        energy(&world.ecs, monster).energy,
+        energy_before - ACTION_COST
+    );
+}
+
+#[test]
+fn monsters_attack_when_next_to_the_player() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@m.#
+        #####
+        ",
+    );
+    let (player, monster) = (world.player, world.monsters[0]);
+    world
+        .ecs
+        .write_storage::<Energy>()
+        .get_mut(monster)
+        .unwrap()
+        .attack_cost = 150;
+    let energy_before = energy(&world.ecs, monster).energy;
+
+    give_turn(&mut world.ecs, monster);
+    run_systems_as(&mut world.ecs, RunState::AwaitingInput);
+
+    assert_eq!(position(&world.ecs, monster), (2, 1));
+    assert!(hp(&world.ecs, player) < 30);
+    // A heavy blow takes longer than a step
+    assert_eq!(energy(&world.ecs, monster).energy, energy_before - 150);
+}
+
+#[test]
+fn monsters_open_doors_in_their_way() {
+    let mut world = world_from_ascii(
+        "
+        #####
+        #@+m#
+        #####
+        ",
+    );
+    let monster = world.monsters[0];
+    let door_idx = world.ecs.fetch::<Map>().xy_idx(2, 1);
+
+    // Nobody can see through a closed door, so pretend the monster heard the player
+    {
+        let mut viewsheds = world.ecs.write_storage::<Viewshed>();
+        let viewshed = viewsheds.get_mut(monster).unwrap();
+        viewshed.visible_tiles = vec![Point::new(1, 1)];
+        viewshed.dirty = false;
+    }
+    give_turn(&mut world.ecs, monster);
+    MonsterAI {}.run_now(&world.ecs);
+
+    // Opening the door takes the monster's move
+    assert!(world.ecs.fetch::<Map>().tiles[door_idx] == TileType::OpenDoor);
+    assert_eq!(position(&world.ecs, monster), (3, 1));
+    let viewsheds = world.ecs.read_storage::<Viewshed>();
+    assert!(viewsheds.join().all(|viewshed| viewshed.dirty));
+}
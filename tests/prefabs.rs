@@ -0,0 +1,26 @@
+use dworld_rust::*;
+use specs::prelude::*;
+
+#[test]
+fn prefab_glyphs_spawn_what_the_raws_draw_with_them() {
+    let mut gs = State::new(
+        MapGenSettings {
+            builder_name: Some("fortress".to_string()),
+            width: 80,
+            height: 43,
+        },
+        Some(1),
+    );
+    gs.new_game();
+
+    let names = gs.ecs.read_storage::<Name>();
+    let count = |prefix: &str| {
+        names
+            .join()
+            .filter(|name| name.name.starts_with(prefix))
+            .count()
+    };
+    // Everyone in the fortress is drawn into it, and nothing else spawns there
+    assert_eq!(count("Goblin"), 4);
+    assert_eq!(count("Orc"), 5);
+}
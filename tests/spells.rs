@@ -0,0 +1,209 @@
+mod support;
+
+use dworld_rust::*;
+use rltk::Point;
+use specs::prelude::*;
+use support::*;
+
+fn spell(name: &str, mana_cost: i32, range: Option<i32>) -> Spell {
+    Spell {
+        name: name.to_string(),
+        description: String::new(),
+        mana_cost,
+        range,
+        radius: 0,
+        damage: None,
+        healing: None,
+        slows: None,
+    }
+}
+
+/// Makes the given spells the only ones the player knows.
+fn teach(world: &mut TestWorld, spells: Vec<Spell>) {
+    world
+        .ecs
+        .write_storage::<KnownSpells>()
+        .insert(world.player, KnownSpells { spells })
+        .expect("Unable to teach spells");
+}
+
+#[test]
+fn bolts_cost_mana_and_are_lessened_by_magic_resistance() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    let monster = world.monsters[0];
+    world
+        .ecs
+        .write_storage::<CombatStats>()
+        .get_mut(monster)
+        .unwrap()
+        .magic_res = 3;
+    teach(
+        &mut world,
+        vec![Spell {
+            damage: Some(8),
+            ..spell("Bolt", 5, Some(6))
+        }],
+    );
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::CastSpell {
+            spell: 0,
+            target: Some(Point::new(5, 1)),
+        },
+    );
+
+    assert_eq!(hp(&world.ecs, monster), 5);
+    assert_eq!(stats(&world.ecs, world.player).curr_mana, 45);
+    let log = log_entries(&world.ecs);
+    assert!(log.contains(&format!("You cast Bolt on Monster #{}.", monster.id())));
+    assert!(log.contains(&format!("Bolt hits Monster #{}, for 5 hp.", monster.id())));
+}
+
+#[test]
+fn spells_need_enough_mana() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    teach(
+        &mut world,
+        vec![Spell {
+            healing: Some(10),
+            ..spell("Mend", 60, None)
+        }],
+    );
+
+    let newrunstate = handle_command(
+        &mut world.ecs,
+        PlayerCommand::CastSpell {
+            spell: 0,
+            target: None,
+        },
+    );
+
+    assert!(newrunstate == RunState::AwaitingInput);
+    assert_eq!(stats(&world.ecs, world.player).curr_mana, 50);
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 0);
+    assert_eq!(
+        log_entries(&world.ecs).last().unwrap(),
+        "You don't have enough mana to cast Mend."
+    );
+}
+
+#[test]
+fn healing_spells_are_cast_on_the_caster() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    world
+        .ecs
+        .write_storage::<CombatStats>()
+        .get_mut(world.player)
+        .unwrap()
+        .curr_hp = 25;
+    teach(
+        &mut world,
+        vec![Spell {
+            healing: Some(10),
+            ..spell("Mend", 10, None)
+        }],
+    );
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::CastSpell {
+            spell: 0,
+            target: Some(Point::new(5, 1)),
+        },
+    );
+
+    let player = stats(&world.ecs, world.player);
+    assert_eq!((player.curr_hp, player.curr_mana), (30, 40));
+    assert!(log_entries(&world.ecs).contains(&"You cast Mend, healing 5 hp.".to_string()));
+}
+
+#[test]
+fn blasts_hit_everyone_in_the_radius() {
+    let mut world = world_from_ascii(
+        "
+        ##########
+        #@.......#
+        #....m...#
+        #.....m.m#
+        ##########
+        ",
+    );
+    let (near, centre, far) = (world.monsters[0], world.monsters[1], world.monsters[2]);
+    teach(
+        &mut world,
+        vec![Spell {
+            radius: 1,
+            damage: Some(6),
+            ..spell("Blast", 15, Some(8))
+        }],
+    );
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::CastSpell {
+            spell: 0,
+            target: Some(Point::new(6, 3)),
+        },
+    );
+
+    assert_eq!(hp(&world.ecs, near), 4);
+    assert_eq!(hp(&world.ecs, centre), 4);
+    assert_eq!(hp(&world.ecs, far), 10);
+    assert_eq!(hp(&world.ecs, world.player), 30);
+}
+
+#[test]
+fn slowed_monsters_gain_energy_at_half_speed() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    let monster = world.monsters[0];
+    teach(
+        &mut world,
+        vec![Spell {
+            slows: Some(2),
+            ..spell("Slow", 8, Some(6))
+        }],
+    );
+    {
+        let mut energies = world.ecs.write_storage::<Energy>();
+        energies.get_mut(world.player).unwrap().energy = ACTION_COST;
+        energies.get_mut(monster).unwrap().energy = 0;
+    }
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::CastSpell {
+            spell: 0,
+            target: Some(Point::new(5, 1)),
+        },
+    );
+
+    // The player needed ten ticks to be ready again, and the monster got only half as far
+    assert_eq!(energy(&world.ecs, world.player).energy, ACTION_COST);
+    assert_eq!(energy(&world.ecs, monster).energy, 50);
+    assert!(world.ecs.read_storage::<Slowed>().get(monster).is_some());
+}
@@ -0,0 +1,282 @@
+//! Shared helpers for the integration tests: a small World built from an ASCII map, holding a
+//! player and monsters with known stats, and ways to play it turn by turn.
+
+// Every test file only uses some of these
+#![allow(dead_code)]
+
+use dworld_rust::*;
+use rltk::{Point, RandomNumberGenerator, RGB};
+use specs::prelude::*;
+
+/// The RNG is seeded, so tests relying on dice rolls always see the same ones.
+pub const SEED: u64 = 1;
+
+pub struct TestWorld {
+    pub ecs: World,
+    pub player: Entity,
+    /// In the order they appear in the map, row by row.
+    pub monsters: Vec<Entity>,
+    /// Likewise.
+    pub items: Vec<Entity>,
+}
+
+/// Builds a World around a map drawn as ASCII art. Every line must be the same length.
+///
+/// * `#` wall, `.` floor, `>` down stairs
+/// * `+` closed door, `'` open door
+/// * `@` the player and `m` a monster, both standing on floor
+/// * `!` an item lying on the floor
+///
+/// There must be exactly one player.
+pub fn world_from_ascii(ascii: &str) -> TestWorld {
+    let lines: Vec<&str> = ascii
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect();
+    let width = lines[0].len() as i32;
+    let height = lines.len() as i32;
+    let mut map = Map::new(1, width, height);
+
+    let mut player_pos = None;
+    let mut monster_positions = Vec::new();
+    let mut item_positions = Vec::new();
+    for (y, line) in lines.iter().enumerate() {
+        assert_eq!(
+            line.len() as i32,
+            width,
+            "Map rows must all be the same length"
+        );
+        for (x, glyph) in line.chars().enumerate() {
+            let (x, y) = (x as i32, y as i32);
+            let idx = map.xy_idx(x, y);
+            map.tiles[idx] = match glyph {
+                '#' => TileType::Wall,
+                '.' => TileType::Floor,
+                '>' => TileType::DownStairs,
+                '+' => TileType::ClosedDoor,
+                '\'' => TileType::OpenDoor,
+                '@' => {
+                    player_pos = Some((x, y));
+                    TileType::Floor
+                }
+                'm' => {
+                    monster_positions.push((x, y));
+                    TileType::Floor
+                }
+                '!' => {
+                    item_positions.push((x, y));
+                    TileType::Floor
+                }
+                _ => panic!("Unknown map glyph '{}'", glyph),
+            };
+        }
+    }
+    map.populate_blocked();
+
+    let mut ecs = World::new();
+    register_components(&mut ecs);
+    ecs.insert(map);
+    ecs.insert(GameLog::new());
+    ecs.insert(RandomNumberGenerator::seeded(SEED));
+    ecs.insert(RunState::AwaitingInput);
+
+    let (player_x, player_y) = player_pos.expect("Map has no player");
+    let player = spawn_player(&mut ecs, player_x, player_y);
+    let monsters = monster_positions
+        .iter()
+        .map(|(x, y)| spawn_monster(&mut ecs, *x, *y))
+        .collect();
+    let items = item_positions
+        .iter()
+        .map(|(x, y)| spawn_item(&mut ecs, *x, *y))
+        .collect();
+
+    // Like a new game, everything gets a look around before the player's first move
+    run_systems_as(&mut ecs, RunState::PreRun);
+    run_ticks(&mut ecs);
+
+    TestWorld {
+        ecs,
+        player,
+        monsters,
+        items,
+    }
+}
+
+/// Spawns the player, and makes them the `Entity` and `Point` resources.
+pub fn spawn_player(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let player = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('@'),
+            fg: RGB::named(rltk::YELLOW),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 0,
+        })
+        .with(Player {})
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 8,
+            dirty: true,
+        })
+        .with(Name {
+            name: "Player".to_string(),
+        })
+        .with(CombatStats {
+            max_hp: 30,
+            curr_hp: 30,
+            defense: 2,
+            magic_res: 4,
+            power: 5,
+            max_mana: 50,
+            curr_mana: 50,
+        })
+        .with(Energy {
+            speed: NORMAL_SPEED,
+            energy: ACTION_COST,
+            attack_cost: ACTION_COST,
+        })
+        .build();
+    ecs.insert(player);
+    ecs.insert(Point::new(x, y));
+    player
+}
+
+/// Spawns a monster that blocks its tile, named after its entity like the real ones.
+pub fn spawn_monster(ecs: &mut World, x: i32, y: i32) -> Entity {
+    let monster = ecs
+        .create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('m'),
+            fg: RGB::named(rltk::RED),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 1,
+        })
+        .with(Monster {})
+        .with(Viewshed {
+            visible_tiles: Vec::new(),
+            range: 8,
+            dirty: true,
+        })
+        .with(CombatStats {
+            max_hp: 10,
+            curr_hp: 10,
+            defense: 1,
+            magic_res: 0,
+            power: 4,
+            max_mana: 0,
+            curr_mana: 0,
+        })
+        .with(Energy {
+            speed: NORMAL_SPEED,
+            energy: 0,
+            attack_cost: ACTION_COST,
+        })
+        .with(BlocksTile {})
+        .build();
+    ecs.write_storage::<Name>()
+        .insert(
+            monster,
+            Name {
+                name: format!("Monster #{}", monster.id()),
+            },
+        )
+        .expect("Unable to name monster");
+    monster
+}
+
+/// Spawns an item on the floor, called "Potion".
+pub fn spawn_item(ecs: &mut World, x: i32, y: i32) -> Entity {
+    ecs.create_entity()
+        .with(Position { x, y })
+        .with(Renderable {
+            glyph: rltk::to_cp437('!'),
+            fg: RGB::named(rltk::MAGENTA),
+            bg: RGB::named(rltk::BLACK),
+            render_order: 2,
+        })
+        .with(Name {
+            name: "Potion".to_string(),
+        })
+        .with(Item {})
+        .build()
+}
+
+/// Puts a new "Potion" straight into `owner`'s backpack.
+pub fn give_item(ecs: &mut World, owner: Entity) -> Entity {
+    let item = spawn_item(ecs, 0, 0);
+    ecs.write_storage::<Position>().remove(item);
+    ecs.write_storage::<InBackpack>()
+        .insert(item, InBackpack { owner })
+        .expect("Unable to insert backpack entry");
+    item
+}
+
+/// Runs every system once, as the game does in the given state.
+pub fn run_systems_as(ecs: &mut World, runstate: RunState) {
+    *ecs.write_resource::<RunState>() = runstate;
+    run_systems(ecs);
+}
+
+/// Lets an entity act the next time the systems run, as if `InitiativeSystem` had picked it.
+pub fn give_turn(ecs: &mut World, entity: Entity) {
+    ecs.write_storage::<MyTurn>()
+        .insert(entity, MyTurn {})
+        .expect("Unable to insert turn");
+}
+
+pub fn energy(ecs: &World, entity: Entity) -> Energy {
+    ecs.read_storage::<Energy>()
+        .get(entity)
+        .expect("Entity has no energy")
+        .clone()
+}
+
+/// Carries out the player's command, then lets time pass the way `State::step` does until it is
+/// the player's turn again. The dead are removed along the way.
+pub fn play_turn(ecs: &mut World, command: PlayerCommand) {
+    if handle_command(ecs, command) == RunState::Ticking {
+        run_ticks(ecs);
+    }
+}
+
+pub fn position(ecs: &World, entity: Entity) -> (i32, i32) {
+    let positions = ecs.read_storage::<Position>();
+    let pos = positions.get(entity).expect("Entity has no position");
+    (pos.x, pos.y)
+}
+
+/// The items `owner` is carrying.
+pub fn backpack(ecs: &World, owner: Entity) -> Vec<Entity> {
+    let entities = ecs.entities();
+    let backpack = ecs.read_storage::<InBackpack>();
+    (&entities, &backpack)
+        .join()
+        .filter(|(_, carried)| carried.owner == owner)
+        .map(|(entity, _)| entity)
+        .collect()
+}
+
+pub fn stats(ecs: &World, entity: Entity) -> CombatStats {
+    ecs.read_storage::<CombatStats>()
+        .get(entity)
+        .expect("Entity has no stats")
+        .clone()
+}
+
+pub fn hp(ecs: &World, entity: Entity) -> i32 {
+    ecs.read_storage::<CombatStats>()
+        .get(entity)
+        .expect("Entity has no stats")
+        .curr_hp
+}
+
+pub fn log_entries(ecs: &World) -> Vec<String> {
+    ecs.fetch::<GameLog>()
+        .entries()
+        .map(|entry| entry.text())
+        .collect()
+}
@@ -0,0 +1,132 @@
+mod support;
+
+use dworld_rust::*;
+use rltk::Point;
+use specs::prelude::*;
+use support::*;
+
+/// Gives the player a consumable item that hits whatever is on the target tile for 6 damage.
+fn give_wand(world: &mut TestWorld, range: i32) -> Entity {
+    let wand = give_item(&mut world.ecs, world.player);
+    world
+        .ecs
+        .write_storage::<Ranged>()
+        .insert(wand, Ranged { range })
+        .unwrap();
+    world
+        .ecs
+        .write_storage::<InflictsDamage>()
+        .insert(wand, InflictsDamage { damage: 6 })
+        .unwrap();
+    world
+        .ecs
+        .write_storage::<Consumable>()
+        .insert(wand, Consumable {})
+        .unwrap();
+    wand
+}
+
+#[test]
+fn ranged_items_hit_the_target_tile() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    let monster = world.monsters[0];
+    let wand = give_wand(&mut world, 5);
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: wand,
+            target: Some(Point::new(5, 1)),
+        },
+    );
+
+    assert_eq!(hp(&world.ecs, monster), 4);
+    assert!(!world.ecs.is_alive(wand));
+    let log = log_entries(&world.ecs);
+    assert!(log.contains(&format!("You use the Potion on Monster #{}.", monster.id())));
+    assert!(log.contains(&format!("Potion hits Monster #{}, for 6 hp.", monster.id())));
+}
+
+#[test]
+fn targets_must_be_in_range_and_in_sight() {
+    let mut world = world_from_ascii(
+        "
+        ##########
+        #@...m.#.#
+        ##########
+        ",
+    );
+    let wand = give_wand(&mut world, 3);
+
+    let in_range = tiles_in_range(&world.ecs, 3);
+    assert!(in_range.contains(&Point::new(4, 1)));
+    assert!(!in_range.contains(&Point::new(5, 1)));
+    assert!(!in_range.contains(&Point::new(8, 1)));
+
+    // Out of range, behind a wall, and no target at all
+    for target in [Some(Point::new(5, 1)), Some(Point::new(8, 1)), None] {
+        let newrunstate = handle_command(
+            &mut world.ecs,
+            PlayerCommand::UseItem { item: wand, target },
+        );
+        assert!(newrunstate == RunState::AwaitingInput);
+    }
+    assert!(world.ecs.is_alive(wand));
+    assert_eq!(world.ecs.fetch::<GameLog>().turn, 0);
+}
+
+#[test]
+fn other_items_ignore_the_target() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    let monster = world.monsters[0];
+    let potion = give_item(&mut world.ecs, world.player);
+    world
+        .ecs
+        .write_storage::<InflictsDamage>()
+        .insert(potion, InflictsDamage { damage: 3 })
+        .unwrap();
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::UseItem {
+            item: potion,
+            target: Some(Point::new(5, 1)),
+        },
+    );
+
+    assert_eq!(hp(&world.ecs, monster), 10);
+    assert_eq!(hp(&world.ecs, world.player), 27);
+}
+
+#[test]
+fn the_cursor_stays_on_the_map_and_on_screen() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@...m.#
+        ########
+        ",
+    );
+    assert_eq!(clamp_to_view(&world.ecs, -1, 1), (0, 1));
+    assert_eq!(clamp_to_view(&world.ecs, 9, 5), (7, 2));
+
+    // On a map larger than the screen, the cursor stops at the edge of the view
+    world.ecs.insert(Map::new(1, 200, 100));
+    assert_eq!(
+        clamp_to_view(&world.ecs, 100, 50),
+        (VIEW_WIDTH - 1, VIEW_HEIGHT - 1)
+    );
+    assert_eq!(clamp_to_view(&world.ecs, 3, -2), (3, 0));
+}
@@ -0,0 +1,101 @@
+mod support;
+
+use dworld_rust::*;
+use rltk::Point;
+use specs::prelude::*;
+use support::*;
+
+fn can_see(ecs: &World, entity: Entity, x: i32, y: i32) -> bool {
+    ecs.read_storage::<Viewshed>()
+        .get(entity)
+        .unwrap()
+        .visible_tiles
+        .contains(&Point::new(x, y))
+}
+
+#[test]
+fn walls_block_sight() {
+    let world = world_from_ascii(
+        "
+        #######
+        #@.#.m#
+        #######
+        ",
+    );
+
+    assert!(can_see(&world.ecs, world.player, 2, 1));
+    assert!(!can_see(&world.ecs, world.player, 5, 1));
+    assert!(!can_see(&world.ecs, world.monsters[0], 1, 1));
+}
+
+#[test]
+fn the_player_reveals_what_they_see() {
+    let world = world_from_ascii(
+        "
+        #######
+        #@.+.m#
+        #######
+        ",
+    );
+    let map = world.ecs.fetch::<Map>();
+
+    // The door itself can be seen, but not what lies behind it
+    for (x, visible) in [(1, true), (2, true), (3, true), (4, false), (5, false)] {
+        let idx = map.xy_idx(x, 1);
+        assert_eq!(map.visible_tiles[idx], visible, "tile {}", x);
+        assert_eq!(map.revealed_tiles[idx], visible, "tile {}", x);
+    }
+}
+
+#[test]
+fn only_dirty_viewsheds_are_recomputed() {
+    let world = world_from_ascii(
+        "
+        #######
+        #@.+.m#
+        #######
+        ",
+    );
+    let player = world.player;
+    let door_idx = world.ecs.fetch::<Map>().xy_idx(3, 1);
+    world.ecs.fetch_mut::<Map>().open_door(door_idx);
+
+    VisibilitySystem {}.run_now(&world.ecs);
+    assert!(!can_see(&world.ecs, player, 5, 1));
+
+    world
+        .ecs
+        .write_storage::<Viewshed>()
+        .get_mut(player)
+        .unwrap()
+        .dirty = true;
+    VisibilitySystem {}.run_now(&world.ecs);
+    assert!(can_see(&world.ecs, player, 5, 1));
+    // Tiles stay revealed once seen
+    assert!(world.ecs.fetch::<Map>().revealed_tiles[door_idx + 1]);
+}
+
+#[test]
+fn moving_updates_what_the_player_sees() {
+    let mut world = world_from_ascii(
+        "
+        ########
+        #@.#...#
+        #......#
+        ########
+        ",
+    );
+    let player = world.player;
+    assert!(!can_see(&world.ecs, player, 6, 1));
+
+    play_turn(
+        &mut world.ecs,
+        PlayerCommand::Move {
+            delta_x: 0,
+            delta_y: 1,
+        },
+    );
+
+    assert_eq!(position(&world.ecs, player), (1, 2));
+    assert!(can_see(&world.ecs, player, 6, 1));
+}
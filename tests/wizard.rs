@@ -0,0 +1,161 @@
+//! Only built with `cargo test --features wizard`.
+#![cfg(feature = "wizard")]
+
+use dworld_rust::*;
+use rltk::Point;
+use specs::prelude::*;
+
+fn new_game() -> State {
+    let mut gs = State::new(
+        MapGenSettings {
+            builder_name: Some("rooms".to_string()),
+            width: 80,
+            height: 43,
+        },
+        Some(1234),
+    );
+    gs.new_game();
+    gs.run_until_input();
+    gs
+}
+
+fn player_hp(gs: &State) -> (i32, i32) {
+    let player = *gs.ecs.fetch::<Entity>();
+    let combat_stats = gs.ecs.read_storage::<CombatStats>();
+    let stats = combat_stats.get(player).unwrap();
+    (stats.curr_hp, stats.max_hp)
+}
+
+fn monster_count(gs: &State) -> usize {
+    gs.ecs.read_storage::<Monster>().join().count()
+}
+
+#[test]
+fn reveal_shows_the_whole_map() {
+    let mut gs = new_game();
+
+    gs.wizard("reveal").unwrap();
+
+    assert!(gs.ecs.fetch::<Map>().revealed_tiles.iter().all(|r| *r));
+}
+
+#[test]
+fn teleport_moves_the_player_onto_open_floor_only() {
+    let mut gs = new_game();
+    let (floor, wall) = {
+        let map = gs.ecs.fetch::<Map>();
+        let floor = (0..map.tiles.len()).find(|idx| !map.blocked[*idx]).unwrap() as i32;
+        (
+            Point::new(floor % map.width, floor / map.width),
+            Point::new(0, 0),
+        )
+    };
+
+    gs.wizard(&format!("teleport {} {}", floor.x, floor.y))
+        .unwrap();
+    assert_eq!(*gs.ecs.fetch::<Point>(), floor);
+
+    assert!(gs
+        .wizard(&format!("teleport {} {}", wall.x, wall.y))
+        .is_err());
+    assert!(gs.wizard("teleport 500 3").is_err());
+    assert_eq!(*gs.ecs.fetch::<Point>(), floor);
+}
+
+#[test]
+fn spawn_and_kill_monsters() {
+    let mut gs = new_game();
+    gs.wizard("kill").unwrap();
+    let before = monster_count(&gs);
+
+    gs.wizard("spawn orc").unwrap();
+    assert_eq!(monster_count(&gs), before + 1);
+    assert!(gs.wizard("spawn dragon").is_err());
+
+    // The orc appears right next to the player, where they can see it
+    gs.wizard("kill").unwrap();
+    assert_eq!(monster_count(&gs), before);
+}
+
+#[test]
+fn set_hp_and_god_mode() {
+    let mut gs = new_game();
+
+    gs.wizard("hp 50").unwrap();
+    assert_eq!(player_hp(&gs), (50, 50));
+    assert!(gs.wizard("hp 0").is_err());
+
+    gs.wizard("god").unwrap();
+    let player = *gs.ecs.fetch::<Entity>();
+    SufferDamage::new_damage(&mut gs.ecs.write_storage(), player, 100, "a test");
+    run_systems(&mut gs.ecs);
+    assert_eq!(player_hp(&gs), (50, 50));
+}
+
+#[test]
+fn regenerate_keeps_the_depth_and_the_player() {
+    let mut gs = new_game();
+    let player = *gs.ecs.fetch::<Entity>();
+
+    gs.wizard("regen").unwrap();
+
+    assert_eq!(gs.ecs.fetch::<Map>().depth, 1);
+    assert_eq!(*gs.ecs.fetch::<Entity>(), player);
+    assert!(gs.ecs.is_alive(player));
+}
+
+#[test]
+fn unknown_commands_are_reported() {
+    let mut gs = new_game();
+
+    assert!(gs.wizard("fly").is_err());
+
+    let log = gs.ecs.fetch::<GameLog>();
+    assert!(log
+        .entries()
+        .last()
+        .unwrap()
+        .text()
+        .starts_with("Unknown command 'fly'"));
+}
+
+#[test]
+fn carried_items_come_along_to_a_new_level() {
+    let mut gs = new_game();
+    let player = *gs.ecs.fetch::<Entity>();
+    gs.wizard("spawn health potion").unwrap();
+    let potion = {
+        let items = gs.ecs.read_storage::<Item>();
+        let positions = gs.ecs.read_storage::<Position>();
+        let entities = gs.ecs.entities();
+        let player_pos = *gs.ecs.fetch::<Point>();
+        (&entities, &items, &positions)
+            .join()
+            .find(|(_, _, pos)| {
+                (pos.x - player_pos.x).abs() <= 1 && (pos.y - player_pos.y).abs() <= 1
+            })
+            .map(|(entity, _, _)| entity)
+            .unwrap()
+    };
+    let potion_pos = {
+        let positions = gs.ecs.read_storage::<Position>();
+        let pos = positions.get(potion).unwrap();
+        (pos.x, pos.y)
+    };
+    gs.wizard(&format!("teleport {} {}", potion_pos.0, potion_pos.1))
+        .unwrap();
+    gs.submit(PlayerCommand::PickUp);
+    gs.run_until_input();
+
+    gs.wizard("regen").unwrap();
+
+    assert!(gs.ecs.is_alive(potion));
+    assert_eq!(
+        gs.ecs
+            .read_storage::<InBackpack>()
+            .get(potion)
+            .unwrap()
+            .owner,
+        player
+    );
+}